@@ -1,4 +1,4 @@
-from typing import List
+from typing import List, Optional, Tuple
 
 class Interval:
     """
@@ -31,7 +31,9 @@ class AIList:
     """
     The augmented interval list (AILIST) object.
 
-    This object will compute region overlaps very efficiently.
+    This object will compute region overlaps very efficiently. Queries treat
+    interval ends as closed, so intervals that only touch the query count as
+    overlapping it.
     """
 
     def __new__(cls, intervals: List[Interval], minimum_coverage_length: int = None) -> AIList:
@@ -47,4 +49,110 @@ class AIList:
         Query the AIList object for overlapping intervals.
 
         :param interval: The interval to query.
-        """
\ No newline at end of file
+        """
+
+    def query_ids(self, interval: Interval) -> List[int]:
+        """
+        Query the AIList object for overlapping intervals, returning their
+        indices in the list the AIList was built from.
+
+        :param interval: The interval to query.
+        """
+
+    def query_batch(self, intervals: List[Interval]) -> List[List[Interval]]:
+        """
+        Query the AIList object with many intervals at once.
+
+        :param intervals: The intervals to query.
+
+        :return: The overlapping intervals for each query, in order.
+        """
+
+    def query_with_min_overlap(self, interval: Interval, fraction: float, reciprocal: bool = False) -> List[Interval]:
+        """
+        Query for overlapping intervals that cover at least a fraction of the query
+        (bedtools ``-f``). With ``reciprocal``, the fraction must also hold for each hit (bedtools ``-r``).
+        Hits that only touch the query are dropped, unless the query or the hit is empty.
+
+        :param interval: The interval to query.
+        :param fraction: The minimum overlapping fraction, between 0 and 1.
+        :param reciprocal: Whether the fraction must also hold for the hit.
+        """
+
+    def count(self, interval: Interval) -> int:
+        """
+        Count the intervals overlapping the query, without building the result list.
+
+        Interval ends are treated as closed, so an interval that ends exactly where
+        the query starts is counted. Book-ended BED intervals count as overlapping.
+
+        :param interval: The interval to query.
+        """
+
+    def any(self, interval: Interval) -> bool:
+        """
+        Check whether any interval overlaps the query.
+
+        As with ``count``, an interval that ends exactly where the query starts is a hit.
+
+        :param interval: The interval to query.
+        """
+
+    def closest(self, interval: Interval, k: int = 1) -> List[Tuple[Interval, int]]:
+        """
+        Find the intervals nearest to the query.
+
+        Distances are signed: 0 for overlaps, negative for intervals ending before
+        the query, and positive for intervals starting after it.
+
+        :param interval: The interval to query.
+        :param k: The number of intervals to return.
+        """
+
+    def merged(self) -> List[Interval]:
+        """
+        The union of the stored intervals, with overlapping and book-ended intervals merged.
+        """
+
+    def coverage(self) -> int:
+        """
+        The total number of bases covered by at least one stored interval.
+        """
+
+    def window_counts(self, length: int, size: int, step: Optional[int] = None) -> List[int]:
+        """
+        Tile ``[0, length)`` into windows and count the intervals overlapping each one.
+
+        Counts match ``count``: an interval ending exactly at a window's start is
+        counted in that window.
+
+        :param length: The length of the chromosome to tile.
+        :param size: The window size.
+        :param step: The distance between window starts. Defaults to ``size``.
+
+        :return: The overlap count for each window, in order.
+        """
+
+def intersect(a: List[Interval], b: AIList) -> List[Interval]:
+    """
+    Clip each interval in ``a`` to the parts covered by ``b`` (A∩B).
+
+    :param a: The intervals to intersect.
+    :param b: The AIList to intersect them with.
+    """
+
+def subtract(a: List[Interval], b: AIList) -> List[Interval]:
+    """
+    Remove the parts of each interval in ``a`` that are covered by ``b`` (A∖B).
+
+    :param a: The intervals to subtract from.
+    :param b: The AIList to subtract.
+    """
+
+def union(a: List[Interval], b: List[Interval]) -> List[Interval]:
+    """
+    Merge two lists of intervals into their union (A∪B).
+
+    :param a: The first list of intervals.
+    :param b: The second list of intervals.
+    """
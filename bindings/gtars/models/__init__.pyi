@@ -7,3 +7,4 @@ class Region:
 
 class RegionSet:
     regions: List[Region]
+    identifier: str
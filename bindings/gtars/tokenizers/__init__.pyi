@@ -1,4 +1,4 @@
-from typing import List, Tuple, Iterator
+from typing import Dict, List, Optional, Tuple, Iterator
 
 class Universe:
     """
@@ -159,6 +159,12 @@ class RegionSet:
 
         :param path: The path to the BED file.
         """
+
+    @property
+    def identifier(self) -> str:
+        """
+        The BEDbase identifier (digest) of the regions, computed in memory.
+        """
     
     def __repr__(self) -> str: ...
 
@@ -224,6 +230,46 @@ class TreeTokenizer:
 
         :param path: The path to the universe file. This should be a BED file.
         """
+
+    @staticmethod
+    def from_config(path: str) -> TreeTokenizer:
+        """
+        Construct a new TreeTokenizer from a yaml config file. The config names the universe
+        file (relative to the config file) and any additional special tokens.
+
+        :param path: The path to the config file.
+        """
+
+    def set_overlap_options(self, min_fraction: float = 0.0, min_bp: int = 0, policy: str = "all") -> None:
+        """
+        Require a minimum overlap before a region is assigned a universe region's token.
+
+        :param min_fraction: The minimum fraction of the query region (between 0 and 1) that must be overlapped.
+        :param min_bp: The minimum number of overlapping base pairs.
+        :param policy: ``"all"`` to keep every passing universe region, ``"best"`` to keep only the largest overlap.
+        """
+
+    def additional_special_token_id(self, token: str) -> Optional[int]:
+        """
+        Get the id of an additional special token from the config.
+
+        :param token: The name of the token.
+        """
+
+    @staticmethod
+    def from_huggingface_json(path: str) -> TreeTokenizer:
+        """
+        Load a tokenizer from a HuggingFace tokenizer.json file.
+
+        :param path: The path to the tokenizer.json file.
+        """
+
+    def to_huggingface_json(self, path: str) -> None:
+        """
+        Write the tokenizer as a HuggingFace tokenizer.json file.
+
+        :param path: The path to write the tokenizer.json file to.
+        """
     
     def unknown_token(self) -> Region:
         """
@@ -327,11 +373,39 @@ class TreeTokenizer:
         :return: The integer representation of the tokenized regions.
         """
 
-    def decode(self, ids: List[int]) -> List[Region]:
+    def encode_plus(
+        self,
+        regions: List[Region],
+        max_length: Optional[int] = None,
+        truncation_side: str = "right",
+        padding: str = "do_not_pad",
+    ) -> Tuple[List[int], List[int]]:
+        """
+        Encode a list of regions with truncation and padding.
+
+        :param regions: The regions to encode.
+        :param max_length: Truncate to this many tokens. Also the length to pad to with ``padding="max_length"``.
+        :param truncation_side: Keep the first (``"right"``) or last (``"left"``) ``max_length`` tokens.
+        :param padding: One of ``"do_not_pad"``, ``"longest"``, or ``"max_length"``.
+
+        :return: The token ids and their attention mask (1 for real tokens, 0 for padding).
+        """
+
+    def encode_batch(self, documents: List[List[Region]]) -> List[List[int]]:
+        """
+        Encode many lists of regions at once. Documents are tokenized in parallel.
+
+        :param documents: The lists of regions to encode.
+
+        :return: The integer representation of each document, in order.
+        """
+
+    def decode(self, ids: List[int], skip_special_tokens: bool = False) -> List[Region]:
         """
         Decode a list of integer representations of the tokenized regions.
 
         :param ids: The integer representations of the tokenized regions.
+        :param skip_special_tokens: Whether to leave special tokens out of the output.
 
         :return: The decoded regions.
         """
@@ -383,4 +457,13 @@ class FragmentTokenizer:
         :param file_path: The path to the file containing fragments.
         :param out_path: The path to the output file. If None, the output is written to the standard output.
         :param filter: A list of chromosomes to filter. If None, all chromosomes are included.
-        """
\ No newline at end of file
+        """
+    def tokenize_fragments_by_barcode(self, file_path: str, sort: bool = False) -> Dict[str, TokenizedRegionSet]:
+        """
+        Tokenize a file containing fragments into one token sequence per cell barcode.
+
+        :param file_path: The path to the file containing fragments.
+        :param sort: Whether to order each cell's fragments by genomic position before tokenizing.
+
+        :return: A dictionary mapping each barcode to its tokens.
+        """
@@ -1,3 +1,5 @@
+use anyhow::Result;
+use gtars::ailist::consts::DEFAULT_MINIMUM_COVERAGE_LENGTH;
 use gtars::ailist::{AIList, Interval};
 use pyo3::{prelude::*, pyclass};
 
@@ -22,7 +24,10 @@ impl PyAIList {
                 end: x.end,
             })
             .collect();
-        let ailist = AIList::new(&mut interval_list, minimum_coverage_length.unwrap_or(3));
+        let ailist = AIList::new(
+            &mut interval_list,
+            minimum_coverage_length.unwrap_or(DEFAULT_MINIMUM_COVERAGE_LENGTH),
+        );
         PyAIList { ailist }
     }
     fn query(&self, py_interval: &PyInterval) -> Vec<PyInterval> {
@@ -39,6 +44,125 @@ impl PyAIList {
             })
             .collect()
     }
+
+    fn query_ids(&self, py_interval: &PyInterval) -> Vec<usize> {
+        let interval: Interval = Interval {
+            start: py_interval.start,
+            end: py_interval.end,
+        };
+        self.ailist.query_ids(&interval)
+    }
+    fn query_batch(&self, py_interval_list: Vec<PyRef<PyInterval>>) -> Vec<Vec<PyInterval>> {
+        self.ailist
+            .query_batch(&to_intervals(py_interval_list))
+            .into_iter()
+            .map(to_py_intervals)
+            .collect()
+    }
+    fn query_with_min_overlap(
+        &self,
+        py_interval: &PyInterval,
+        fraction: f64,
+        reciprocal: Option<bool>,
+    ) -> Vec<PyInterval> {
+        let interval: Interval = Interval {
+            start: py_interval.start,
+            end: py_interval.end,
+        };
+        self.ailist
+            .query_with_min_overlap(&interval, fraction, reciprocal.unwrap_or(false))
+            .into_iter()
+            .map(|x| PyInterval {
+                start: x.start,
+                end: x.end,
+            })
+            .collect()
+    }
+    fn count(&self, py_interval: &PyInterval) -> usize {
+        let interval: Interval = Interval {
+            start: py_interval.start,
+            end: py_interval.end,
+        };
+        self.ailist.count(&interval)
+    }
+    fn any(&self, py_interval: &PyInterval) -> bool {
+        let interval: Interval = Interval {
+            start: py_interval.start,
+            end: py_interval.end,
+        };
+        self.ailist.any(&interval)
+    }
+    fn closest(&self, py_interval: &PyInterval, k: Option<usize>) -> Vec<(PyInterval, i64)> {
+        let interval: Interval = Interval {
+            start: py_interval.start,
+            end: py_interval.end,
+        };
+        self.ailist
+            .closest_interval(&interval, k.unwrap_or(1))
+            .into_iter()
+            .map(|(x, distance)| {
+                (
+                    PyInterval {
+                        start: x.start,
+                        end: x.end,
+                    },
+                    distance,
+                )
+            })
+            .collect()
+    }
+    fn merged(&self) -> Vec<PyInterval> {
+        self.ailist
+            .merged()
+            .into_iter()
+            .map(|x| PyInterval {
+                start: x.start,
+                end: x.end,
+            })
+            .collect()
+    }
+    fn coverage(&self) -> u64 {
+        self.ailist.coverage()
+    }
+    fn window_counts(&self, length: u32, size: u32, step: Option<u32>) -> Result<Vec<usize>> {
+        self.ailist
+            .window_counts(length, size, step.unwrap_or(size))
+    }
+}
+
+fn to_intervals(py_interval_list: Vec<PyRef<PyInterval>>) -> Vec<Interval> {
+    py_interval_list
+        .into_iter()
+        .map(|x| Interval {
+            start: x.start,
+            end: x.end,
+        })
+        .collect()
+}
+
+fn to_py_intervals(interval_list: Vec<Interval>) -> Vec<PyInterval> {
+    interval_list
+        .into_iter()
+        .map(|x| PyInterval {
+            start: x.start,
+            end: x.end,
+        })
+        .collect()
+}
+
+#[pyfunction]
+fn intersect(a: Vec<PyRef<PyInterval>>, b: PyRef<PyAIList>) -> Vec<PyInterval> {
+    to_py_intervals(gtars::ailist::intersect(&to_intervals(a), &b.ailist))
+}
+
+#[pyfunction]
+fn subtract(a: Vec<PyRef<PyInterval>>, b: PyRef<PyAIList>) -> Vec<PyInterval> {
+    to_py_intervals(gtars::ailist::subtract(&to_intervals(a), &b.ailist))
+}
+
+#[pyfunction]
+fn union(a: Vec<PyRef<PyInterval>>, b: Vec<PyRef<PyInterval>>) -> Vec<PyInterval> {
+    to_py_intervals(gtars::ailist::union(&to_intervals(a), &to_intervals(b)))
 }
 
 /// A Python module implemented in Rust.
@@ -46,5 +170,8 @@ impl PyAIList {
 pub fn ailist(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyInterval>()?;
     m.add_class::<PyAIList>()?;
+    m.add_wrapped(wrap_pyfunction!(intersect))?;
+    m.add_wrapped(wrap_pyfunction!(subtract))?;
+    m.add_wrapped(wrap_pyfunction!(union))?;
     Ok(())
 }
@@ -6,6 +6,7 @@ use numpy::ndarray::Array;
 use numpy::{IntoPyArray, PyArray1};
 
 use anyhow::Result;
+use gtars::common::models::RegionSet;
 use gtars::common::utils::extract_regions_from_bed_file;
 
 use crate::models::{PyRegion, PyTokenizedRegion, PyUniverse};
@@ -39,6 +40,17 @@ impl PyRegionSet {
         })
     }
 
+    #[getter]
+    pub fn identifier(&self) -> Result<String> {
+        let rs = RegionSet::from(
+            self.regions
+                .iter()
+                .map(|r| r.to_region())
+                .collect::<Vec<_>>(),
+        );
+        Ok(rs.identifier())
+    }
+
     pub fn __repr__(&self) -> String {
         format!("RegionSet({} regions)", self.regions.len())
     }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use gtars::tokenizers::FragmentTokenizer;
 use gtars::tokenizers::TreeTokenizer;
 use pyo3::prelude::*;
@@ -85,4 +87,31 @@ impl PyFragmentTokenizer {
             }
         }
     }
+
+    pub fn tokenize_fragments_by_barcode(
+        &self,
+        file: String,
+        sort: Option<bool>,
+    ) -> PyResult<HashMap<String, PyTokenizedRegionSet>> {
+        let path = std::path::Path::new(&file);
+        let cells = self
+            .tokenizer
+            .tokenize_fragments_by_barcode(path, sort.unwrap_or(false))?;
+        Python::with_gil(|py| {
+            let py_cells = cells
+                .into_iter()
+                .map(|(barcode, trs)| {
+                    (
+                        barcode,
+                        PyTokenizedRegionSet {
+                            ids: trs.ids,
+                            curr: 0,
+                            universe: self.universe.clone_ref(py),
+                        },
+                    )
+                })
+                .collect();
+            Ok(py_cells)
+        })
+    }
 }
@@ -7,7 +7,10 @@ use anyhow::Result;
 use std::path::Path;
 
 use gtars::common::models::RegionSet;
-use gtars::tokenizers::{Tokenizer, TreeTokenizer};
+use gtars::tokenizers::{
+    EncodePlus, EncodingOptions, OverlapOptions, OverlapPolicy, PaddingStrategy, Tokenizer,
+    TreeTokenizer, TruncationSide,
+};
 
 use crate::models::{PyRegion, PyTokenizedRegionSet, PyUniverse};
 use crate::utils::extract_regions_from_py_any;
@@ -35,6 +38,65 @@ impl PyTreeTokenizer {
         })
     }
 
+    #[staticmethod]
+    pub fn from_config(path: String) -> Result<Self> {
+        Python::with_gil(|py| {
+            let path = Path::new(&path);
+            let tokenizer = TreeTokenizer::from_config(path)?;
+            let py_universe: PyUniverse = tokenizer.universe.to_owned().into();
+            let py_universe_bound = Py::new(py, py_universe)?;
+
+            Ok(PyTreeTokenizer {
+                tokenizer,
+                universe: py_universe_bound,
+            })
+        })
+    }
+
+    pub fn set_overlap_options(
+        &mut self,
+        min_fraction: Option<f64>,
+        min_bp: Option<u32>,
+        policy: Option<String>,
+    ) -> Result<()> {
+        let policy = match policy.as_deref().unwrap_or("all") {
+            "all" => OverlapPolicy::All,
+            "best" => OverlapPolicy::Best,
+            other => anyhow::bail!("Unknown overlap policy: {}", other),
+        };
+
+        self.tokenizer.set_overlap_options(OverlapOptions {
+            min_fraction: min_fraction.unwrap_or(0.0),
+            min_bp: min_bp.unwrap_or(0),
+            policy,
+        });
+
+        Ok(())
+    }
+
+    pub fn additional_special_token_id(&self, token: String) -> Option<u32> {
+        self.tokenizer.additional_special_token_id(&token)
+    }
+
+    #[staticmethod]
+    pub fn from_huggingface_json(path: String) -> Result<Self> {
+        Python::with_gil(|py| {
+            let path = Path::new(&path);
+            let tokenizer = TreeTokenizer::from_huggingface_json(path)?;
+            let py_universe: PyUniverse = tokenizer.universe.to_owned().into();
+            let py_universe_bound = Py::new(py, py_universe)?;
+
+            Ok(PyTreeTokenizer {
+                tokenizer,
+                universe: py_universe_bound,
+            })
+        })
+    }
+
+    pub fn to_huggingface_json(&self, path: String) -> Result<()> {
+        self.tokenizer.to_huggingface_json(Path::new(&path))
+    }
+
     #[getter]
     pub fn unknown_token(&self) -> Result<PyRegion> {
         Ok(self.tokenizer.unknown_token().into())
@@ -168,10 +230,66 @@ impl PyTreeTokenizer {
         Ok(tokenized.ids)
     }
 
-    pub fn decode(&self, ids: Vec<u32>) -> Result<Vec<PyRegion>> {
-        let regions = ids
+    // encode with truncation and padding, returning the ids and their attention mask
+    pub fn encode_plus(
+        &self,
+        regions: &Bound<'_, PyAny>,
+        max_length: Option<usize>,
+        truncation_side: Option<String>,
+        padding: Option<String>,
+    ) -> Result<(Vec<u32>, Vec<u8>)> {
+        let rs = extract_regions_from_py_any(regions)?;
+
+        let truncation_side = match truncation_side.as_deref().unwrap_or("right") {
+            "right" => TruncationSide::Right,
+            "left" => TruncationSide::Left,
+            other => anyhow::bail!("Unknown truncation side: {}", other),
+        };
+        let padding = match padding.as_deref().unwrap_or("do_not_pad") {
+            "do_not_pad" => PaddingStrategy::DoNotPad,
+            "longest" => PaddingStrategy::Longest,
+            "max_length" => PaddingStrategy::MaxLength,
+            other => anyhow::bail!("Unknown padding strategy: {}", other),
+        };
+
+        let encoding = self.tokenizer.encode_plus(
+            &rs,
+            &EncodingOptions {
+                max_length,
+                truncation_side,
+                padding,
+                ..Default::default()
+            },
+        )?;
+
+        Ok((encoding.ids, encoding.attention_mask))
+    }
+
+    // encode many documents at once, tokenizing them in parallel
+    pub fn encode_batch(&self, documents: Vec<Bound<'_, PyAny>>) -> Result<Vec<Vec<u32>>> {
+        let documents = documents
             .iter()
-            .map(|id| self.tokenizer.universe.id_to_region[id].clone().into())
+            .map(|regions| Ok(extract_regions_from_py_any(regions)?.regions))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(self
+            .tokenizer
+            .tokenize_batch(&documents)
+            .into_iter()
+            .map(|tokenized| tokenized.ids)
+            .collect())
+    }
+
+    pub fn decode(
+        &self,
+        ids: Vec<u32>,
+        skip_special_tokens: Option<bool>,
+    ) -> Result<Vec<PyRegion>> {
+        let regions = self
+            .tokenizer
+            .decode(&ids, skip_special_tokens.unwrap_or(false))?
+            .into_iter()
+            .map(|region| region.into())
             .collect();
 
         Ok(regions)
@@ -0,0 +1,185 @@
+use std::ffi::OsString;
+use std::io;
+use std::io::Write;
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use super::*;
+use crate::common::models::Region;
+
+pub fn make_overlap_cli() -> Command {
+    Command::new(consts::OVERLAP_CMD)
+        .about("Report overlaps between two bed files, in the style of bedtools intersect. Overlaps are written in A file order, and the overlaps of each A entry in B file order.")
+        .arg(
+            Arg::new("a")
+                .short('a')
+                .help(
+                    "Path to the (possibly gzipped) query bed file. Streamed, so it can be large.",
+                )
+                .required(true),
+        )
+        .arg(
+            Arg::new("b")
+                .short('b')
+                .help("Path to the (possibly gzipped) bed file to overlap with. Indexed in memory.")
+                .required(true),
+        )
+        .arg(
+            Arg::new("wa")
+                .long("wa")
+                .help(
+                    "Write the original A entry for each overlap instead of the overlapping part. Also accepted as -wa.",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("wb")
+                .long("wb")
+                .help("Also write the overlapping B entry after the A entry. Also accepted as -wb.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("count")
+                .long("count")
+                .short('c')
+                .help("Write each A entry once, followed by its number of overlaps in B.")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["wa", "wb", "invert"]),
+        )
+        .arg(
+            Arg::new("invert")
+                .long("invert")
+                .short('v')
+                .help("Only write A entries that have no overlaps in B.")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["wa", "wb"]),
+        )
+}
+
+///
+/// Rewrite bedtools' single-dash `-wa` and `-wb` flags to the `--wa` and `--wb` flags clap
+/// understands, so bedtools command lines work unchanged. Only the arguments of the `overlap`
+/// subcommand are rewritten; every other command line is kept as is.
+///
+/// # Arguments
+/// - args: the command line arguments, including the program name
+pub fn bedtools_style_args<I: IntoIterator<Item = OsString>>(args: I) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    if args.get(1).and_then(|arg| arg.to_str()) != Some(consts::OVERLAP_CMD) {
+        return args;
+    }
+
+    for arg in args.iter_mut().skip(2) {
+        match arg.to_str() {
+            Some("-wa") => *arg = OsString::from("--wa"),
+            Some("-wb") => *arg = OsString::from("--wb"),
+            _ => {}
+        }
+    }
+    args
+}
+
+pub mod handlers {
+
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::*;
+    use crate::common::utils::{extract_regions_from_bed_file, stream_regions_from_bed_file};
+
+    pub fn overlap_bed_files(matches: &ArgMatches) -> Result<()> {
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        write_overlaps(matches, &mut stdout)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+
+    ///
+    /// Write the overlaps `overlap_bed_files` reports to any writer. The A file is
+    /// streamed, so overlaps come out in A file order; the overlaps of each A entry
+    /// are written in B file order, like bedtools does.
+    ///
+    /// # Arguments
+    /// - matches: the parsed arguments of the `overlap` subcommand
+    /// - writer: where to write the overlaps
+    pub fn write_overlaps<W: Write>(matches: &ArgMatches, writer: &mut W) -> Result<()> {
+        let a = matches
+            .get_one::<String>("a")
+            .expect("A file path is required");
+
+        let b = matches
+            .get_one::<String>("b")
+            .expect("B file path is required");
+
+        let write_a = matches.get_flag("wa");
+        let write_b = matches.get_flag("wb");
+        let count = matches.get_flag("count");
+        let invert = matches.get_flag("invert");
+
+        // keep the B regions around so `--wb` can write them back out
+        let b_regions = extract_regions_from_bed_file(Path::new(&b))
+            .with_context(|| "There was an error reading in the B bed file!")?;
+        let overlapper =
+            MultiChromOverlapper::from_regions(&b_regions, consts::DEFAULT_MINIMUM_COVERAGE_LENGTH);
+
+        for a_region in stream_regions_from_bed_file(Path::new(&a))? {
+            let a_region =
+                a_region.with_context(|| "There was an error reading in the A bed file!")?;
+
+            // ids are positions in the B file, the index returns them in its own order
+            let mut ids = overlapper.query_ids(&a_region);
+            ids.sort_unstable();
+
+            // the index treats ends as closed, bed is half-open
+            let hits: Vec<&Region> = ids
+                .into_iter()
+                .map(|id| &b_regions[id])
+                .filter(|b_region| b_region.end > a_region.start)
+                .collect();
+
+            if count {
+                writeln!(writer, "{}\t{}", format_region(&a_region), hits.len())?;
+                continue;
+            }
+
+            if invert {
+                if hits.is_empty() {
+                    writeln!(writer, "{}", format_region(&a_region))?;
+                }
+                continue;
+            }
+
+            for b_region in hits {
+                let reported = if write_a {
+                    a_region.clone()
+                } else {
+                    Region {
+                        chr: a_region.chr.to_owned(),
+                        start: a_region.start.max(b_region.start),
+                        end: a_region.end.min(b_region.end),
+                    }
+                };
+
+                if write_b {
+                    writeln!(
+                        writer,
+                        "{}\t{}",
+                        format_region(&reported),
+                        format_region(b_region)
+                    )?;
+                } else {
+                    writeln!(writer, "{}", format_region(&reported))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn format_region(region: &Region) -> String {
+        format!("{}\t{}\t{}", region.chr, region.start, region.end)
+    }
+}
@@ -1,4 +1,32 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use consts::{AILIST_FORMAT_VERSION, AILIST_HEADER};
+
+pub mod cli;
+pub mod multi_chrom_overlapper;
+
+/// constants for the ailist module.
+pub mod consts {
+    /// command for the `gtars` cli
+    pub const OVERLAP_CMD: &str = "overlap";
+
+    /// magic bytes and format version of saved indexes
+    pub const AILIST_HEADER: &[u8; 4] = b"AILS";
+    pub const MULTI_CHROM_AILIST_HEADER: &[u8; 4] = b"MCAI";
+    pub const AILIST_FORMAT_VERSION: u8 = 0x01;
+
+    /// `minimum_coverage_length` used when building an index for callers that don't choose one
+    pub const DEFAULT_MINIMUM_COVERAGE_LENGTH: usize = 3;
+}
+
+pub use multi_chrom_overlapper::{annotate, MultiChromOverlapper};
 
 pub struct Interval {
     pub start: u32,
@@ -11,31 +39,85 @@ impl fmt::Display for Interval {
     }
 }
 
+///
+/// The number of bytes between the current position of a stream and its end.
+pub(crate) fn remaining_bytes<R: Seek>(reader: &mut R) -> Result<u64> {
+    let position = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+
+    Ok(end.saturating_sub(position))
+}
+
+///
+/// Whether a hit returned by a query passes a minimum overlap threshold. As in bedtools,
+/// the two must share at least one base, unless either is empty: a zero-length interval
+/// has nothing to cover, so being returned by the query is enough.
+///
+/// # Arguments
+/// - query: the start and end of the query
+/// - hit: the start and end of the hit
+/// - fraction: the minimum fraction of the query (between 0 and 1) that must be overlapped
+/// - reciprocal: whether the fraction must also hold for the hit
+pub(crate) fn meets_min_overlap(
+    query: (u32, u32),
+    hit: (u32, u32),
+    fraction: f64,
+    reciprocal: bool,
+) -> bool {
+    let query_length = query.1.saturating_sub(query.0);
+    let hit_length = hit.1.saturating_sub(hit.0);
+    let overlap = hit.1.min(query.1).saturating_sub(hit.0.max(query.0));
+
+    let covers =
+        |length: u32| -> bool { length == 0 || overlap as f64 >= fraction * length as f64 };
+
+    (overlap > 0 || query_length == 0 || hit_length == 0)
+        && covers(query_length)
+        && (!reciprocal || covers(hit_length))
+}
+
+///
+/// An augmented interval list. Queries treat interval ends as closed: an interval overlaps
+/// the query if it starts before the query ends and doesn't end before the query starts. An
+/// interval that ends exactly where the query starts is a hit, so book-ended BED intervals,
+/// which are half-open, count as overlapping. Callers that want BED semantics have to drop
+/// hits with `end == query.start`, as the `overlap` command does.
 pub struct AIList {
     starts: Vec<u32>,
     ends: Vec<u32>,
     max_ends: Vec<u32>,
+    ids: Vec<usize>,
     header_list: Vec<usize>,
 }
 
 impl AIList {
     pub fn new(intervals: &mut Vec<Interval>, minimum_coverage_length: usize) -> AIList {
+        // remember where each interval came from, so hits can be reported
+        // as indices into the original list. both sorts are stable, so
+        // `remaining_ids` lines up with the sorted intervals.
+        let mut remaining_ids: Vec<usize> = (0..intervals.len()).collect();
+        remaining_ids.sort_by_key(|&i| intervals[i].start);
+
         // in the future, clone and sort...
         intervals.sort_by_key(|key| key.start);
 
         let mut starts: Vec<u32> = Vec::new();
         let mut ends: Vec<u32> = Vec::new();
         let mut max_ends: Vec<u32> = Vec::new();
+        let mut ids: Vec<usize> = Vec::new();
         let mut header_list: Vec<usize> = vec![0];
 
         loop {
-            let mut results = Self::decompose(intervals, minimum_coverage_length);
+            let mut results = Self::decompose(intervals, &remaining_ids, minimum_coverage_length);
 
-            starts.append(&mut results.0);
-            ends.append(&mut results.1);
-            max_ends.append(&mut results.2);
+            starts.append(&mut results.starts);
+            ends.append(&mut results.ends);
+            max_ends.append(&mut results.max_ends);
+            ids.append(&mut results.ids);
 
-            *intervals = results.3;
+            *intervals = results.remaining;
+            remaining_ids = results.remaining_ids;
 
             if intervals.is_empty() {
                 break;
@@ -48,19 +130,23 @@ impl AIList {
             starts,
             ends,
             max_ends,
+            ids,
             header_list,
         }
     }
 
     fn decompose(
         intervals: &mut [Interval],
+        interval_ids: &[usize],
         minimum_coverage_length: usize,
-    ) -> (Vec<u32>, Vec<u32>, Vec<u32>, Vec<Interval>) {
+    ) -> Decomposition {
         // look at the next minL*2 intervals
         let mut starts: Vec<u32> = Vec::new();
         let mut ends: Vec<u32> = Vec::new();
         let mut max_ends: Vec<u32> = Vec::new();
+        let mut ids: Vec<usize> = Vec::new();
         let mut l2: Vec<Interval> = Vec::new();
+        let mut l2_ids: Vec<usize> = Vec::new();
 
         for (index, interval) in intervals.iter().enumerate() {
             let mut count = 0;
@@ -79,9 +165,11 @@ impl AIList {
                     start: interval.start,
                     end: interval.end,
                 });
+                l2_ids.push(interval_ids[index]);
             } else {
                 starts.push(interval.start);
-                ends.push(interval.end)
+                ends.push(interval.end);
+                ids.push(interval_ids[index]);
             }
         }
 
@@ -92,7 +180,14 @@ impl AIList {
             max_ends.push(max);
         }
 
-        (starts, ends, max_ends, l2)
+        Decomposition {
+            starts,
+            ends,
+            max_ends,
+            ids,
+            remaining: l2,
+            remaining_ids: l2_ids,
+        }
     }
 
     fn query_slice(
@@ -144,6 +239,581 @@ impl AIList {
 
         results_list
     }
+
+    ///
+    /// Query for overlapping intervals, returning their indices in the list the
+    /// AIList was built from instead of copies of the intervals. Indices carry no
+    /// borrow, so they're easy to hand across FFI boundaries or between threads.
+    ///
+    /// # Arguments
+    /// - interval: the query interval
+    ///
+    /// # Returns
+    /// - indices into the original interval list
+    pub fn query_ids(&self, interval: &Interval) -> Vec<usize> {
+        let mut results_list: Vec<usize> = Vec::new();
+
+        for (starts, ends, max_ends, ids) in self.components() {
+            let mut i = starts.partition_point(|&x| x < interval.end);
+
+            while i > 0 {
+                i -= 1;
+                if interval.start > ends[i] {
+                    if interval.start > max_ends[i] {
+                        break;
+                    }
+                } else {
+                    results_list.push(ids[i]);
+                }
+            }
+        }
+
+        results_list
+    }
+
+    ///
+    /// Query many intervals at once.
+    ///
+    /// # Arguments
+    /// - intervals: the query intervals
+    ///
+    /// # Returns
+    /// - the overlapping intervals for each query, in the same order as `intervals`
+    pub fn query_batch(&self, intervals: &[Interval]) -> Vec<Vec<Interval>> {
+        intervals
+            .iter()
+            .map(|interval| self.query(interval))
+            .collect()
+    }
+
+    ///
+    /// Query for overlapping intervals that cover at least a fraction of the query,
+    /// matching bedtools `intersect -f`. With `reciprocal` set, the overlap must also
+    /// cover that fraction of each hit (bedtools `-r`). Unlike `query`, hits that only
+    /// touch the query are dropped, unless the query or the hit is empty.
+    ///
+    /// # Arguments
+    /// - interval: the query interval
+    /// - fraction: the minimum fraction of the query (between 0 and 1) that must be overlapped
+    /// - reciprocal: whether the fraction must also hold for the hit
+    ///
+    /// # Returns
+    /// - the overlapping intervals that pass the threshold
+    pub fn query_with_min_overlap(
+        &self,
+        interval: &Interval,
+        fraction: f64,
+        reciprocal: bool,
+    ) -> Vec<Interval> {
+        self.query(interval)
+            .into_iter()
+            .filter(|hit| {
+                meets_min_overlap(
+                    (interval.start, interval.end),
+                    (hit.start, hit.end),
+                    fraction,
+                    reciprocal,
+                )
+            })
+            .collect()
+    }
+
+    ///
+    /// Count the intervals overlapping the query without allocating a result vector.
+    /// Intervals ending exactly at the query start are counted, see `AIList`.
+    ///
+    /// # Arguments
+    /// - interval: the query interval
+    ///
+    /// # Returns
+    /// - the number of overlapping intervals
+    pub fn count(&self, interval: &Interval) -> usize {
+        self.components()
+            .map(|(starts, ends, max_ends, _)| Self::count_slice(interval, starts, ends, max_ends))
+            .sum()
+    }
+
+    ///
+    /// Tile `[0, length)` into windows of `size` bases, `step` bases apart, and count the
+    /// intervals overlapping each window. Every interval marks the first and last window
+    /// it overlaps, and one running sum over the windows turns those marks into counts,
+    /// so nothing is sorted or copied and no query is run per window. Counts match `count`,
+    /// so an interval ending exactly at a window's start is counted in that window.
+    ///
+    /// # Arguments
+    /// - length: the length of the chromosome to tile
+    /// - size: the window size; the last window is clipped to `length`
+    /// - step: the distance between window starts
+    ///
+    /// # Returns
+    /// - the overlap count for each window, in order
+    pub fn window_counts(&self, length: u32, size: u32, step: u32) -> Result<Vec<usize>> {
+        let num_windows = Self::num_windows(length, size, step)?;
+        let (size, step) = (size as u64, step as u64);
+
+        // window j is [j * step, min(j * step + size, length)). like `count`, an interval
+        // overlaps it if it starts before the window ends and doesn't end before it starts
+        let mut opened = vec![0usize; num_windows + 1];
+        let mut closed = vec![0usize; num_windows + 1];
+        for (&start, &end) in self.starts.iter().zip(self.ends.iter()) {
+            if start >= length {
+                continue;
+            }
+            let (start, end) = (start as u64, end as u64);
+
+            let first = if start < size {
+                0
+            } else {
+                (start - size) / step + 1
+            };
+            let last = (end / step).min(num_windows as u64 - 1);
+
+            if first <= last {
+                opened[first as usize] += 1;
+                closed[last as usize + 1] += 1;
+            }
+        }
+
+        let mut running = 0;
+        Ok((0..num_windows)
+            .map(|window| {
+                running = running + opened[window] - closed[window];
+                running
+            })
+            .collect())
+    }
+
+    ///
+    /// The number of windows `window_counts` tiles `[0, length)` into.
+    pub(crate) fn num_windows(length: u32, size: u32, step: u32) -> Result<usize> {
+        if size == 0 || step == 0 {
+            anyhow::bail!("Window size and step must be positive.");
+        }
+
+        Ok((0..length).step_by(step as usize).len())
+    }
+
+    ///
+    /// Check whether any interval overlaps the query. Stops at the first hit. An interval
+    /// ending exactly at the query start is a hit, see `AIList`.
+    ///
+    /// # Arguments
+    /// - interval: the query interval
+    pub fn any(&self, interval: &Interval) -> bool {
+        self.components()
+            .any(|(starts, ends, max_ends, _)| Self::any_slice(interval, starts, ends, max_ends))
+    }
+
+    ///
+    /// Find the interval nearest to a single position.
+    ///
+    /// # Arguments
+    /// - pos: the query position
+    ///
+    /// # Returns
+    /// - the nearest interval and its signed distance (see `closest_interval`), or `None` if the list is empty
+    pub fn closest(&self, pos: u32) -> Option<(Interval, i64)> {
+        let query = Interval {
+            start: pos,
+            end: pos.saturating_add(1),
+        };
+        self.closest_interval(&query, 1).pop()
+    }
+
+    ///
+    /// Find the `k` intervals nearest to the query.
+    ///
+    /// Distances are signed, bedtools `closest -D ref` style: overlapping intervals have a
+    /// distance of 0, intervals ending before the query are negative (the number of bases
+    /// between them), and intervals starting after the query are positive. Results are ordered
+    /// by absolute distance, then by start.
+    ///
+    /// # Arguments
+    /// - interval: the query interval
+    /// - k: the number of intervals to return
+    ///
+    /// # Returns
+    /// - up to `k` `(interval, distance)` pairs
+    pub fn closest_interval(&self, interval: &Interval, k: usize) -> Vec<(Interval, i64)> {
+        let mut candidates: Vec<(Interval, i64)> = self
+            .query(interval)
+            .into_iter()
+            .map(|hit| (hit, 0))
+            .collect();
+
+        for (starts, ends, max_ends, _) in self.components() {
+            Self::closest_slice(interval, k, starts, ends, max_ends, &mut candidates);
+        }
+
+        candidates.sort_by_key(|(hit, distance)| (distance.abs(), hit.start));
+        candidates.truncate(k);
+
+        candidates
+    }
+
+    fn closest_slice(
+        interval: &Interval,
+        k: usize,
+        starts: &[u32],
+        ends: &[u32],
+        max_ends: &[u32],
+        candidates: &mut Vec<(Interval, i64)>,
+    ) {
+        if k == 0 {
+            return;
+        }
+
+        let split = starts.partition_point(|&x| x < interval.end);
+
+        // downstream: starts are sorted, so the first k past the query are the nearest
+        for i in split..(split + k).min(starts.len()) {
+            candidates.push((
+                Interval {
+                    start: starts[i],
+                    end: ends[i],
+                },
+                starts[i] as i64 - interval.end as i64,
+            ));
+        }
+
+        // upstream: ends aren't sorted, so keep the k largest ends seen so far
+        // and stop once max_ends says nothing further back can beat them
+        let mut upstream: BinaryHeap<Reverse<(u32, u32)>> = BinaryHeap::new();
+        let mut i = split;
+        while i > 0 {
+            i -= 1;
+            if upstream.len() == k {
+                let Reverse((worst_end, _)) = upstream.peek().unwrap();
+                if max_ends[i] <= *worst_end {
+                    break;
+                }
+            }
+            if ends[i] < interval.start {
+                upstream.push(Reverse((ends[i], starts[i])));
+                if upstream.len() > k {
+                    upstream.pop();
+                }
+            }
+        }
+
+        for Reverse((end, start)) in upstream {
+            candidates.push((Interval { start, end }, end as i64 - interval.start as i64));
+        }
+    }
+
+    ///
+    /// Collapse the stored intervals into their union. Overlapping and book-ended
+    /// intervals are merged, and the result is sorted by start.
+    ///
+    /// # Returns
+    /// - the merged intervals
+    pub fn merged(&self) -> Vec<Interval> {
+        merge_bounds(
+            self.starts
+                .iter()
+                .zip(self.ends.iter())
+                .map(|(&start, &end)| (start, end))
+                .collect(),
+        )
+    }
+
+    ///
+    /// Total number of bases covered by at least one stored interval.
+    pub fn coverage(&self) -> u64 {
+        self.merged()
+            .iter()
+            .map(|interval| interval.end.saturating_sub(interval.start) as u64)
+            .sum()
+    }
+
+    ///
+    /// Save the built index to disk so it can be reloaded without re-decomposing the intervals.
+    ///
+    /// # Arguments
+    /// - path: the file to write the index to
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| "Failed to create AIList index file!")?;
+        let mut writer = BufWriter::new(file);
+
+        writer
+            .write_all(AILIST_HEADER)
+            .with_context(|| "Failed to write AIList header to file!")?;
+        writer
+            .write_all(&AILIST_FORMAT_VERSION.to_le_bytes())
+            .with_context(|| "Failed to write AIList format version to file!")?;
+        self.write_to(&mut writer)?;
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    ///
+    /// Load an index previously written with `save`.
+    ///
+    /// # Arguments
+    /// - path: the file to read the index from
+    pub fn load(path: &Path) -> Result<AIList> {
+        let file = File::open(path).with_context(|| "Failed to open AIList index file!")?;
+        let mut reader = BufReader::new(file);
+
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        if &header != AILIST_HEADER {
+            anyhow::bail!("File doesn't appear to be a valid AIList index.")
+        }
+
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != AILIST_FORMAT_VERSION {
+            anyhow::bail!("Unsupported AIList index format version: {}", version[0])
+        }
+
+        Self::read_from(&mut reader)
+    }
+
+    ///
+    /// Write the raw index (no header) to a writer. Used to embed AILists in other index files.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&(self.header_list.len() as u64).to_le_bytes())?;
+        for &offset in self.header_list.iter() {
+            writer.write_all(&(offset as u64).to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.starts.len() as u64).to_le_bytes())?;
+        for values in [&self.starts, &self.ends, &self.max_ends] {
+            for value in values.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        for &id in self.ids.iter() {
+            writer.write_all(&(id as u64).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Read a raw index written with `write_to`. The lengths in the index are checked
+    /// against what's left of the stream before anything is allocated for them.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<AIList> {
+        let read_u64 = |reader: &mut R| -> Result<u64> {
+            let mut buffer = [0; 8];
+            reader
+                .read_exact(&mut buffer)
+                .with_context(|| "AIList index ended unexpectedly")?;
+            Ok(u64::from_le_bytes(buffer))
+        };
+        let read_u32s = |reader: &mut R, n: usize| -> Result<Vec<u32>> {
+            let mut buffer = vec![0; n * 4];
+            reader
+                .read_exact(&mut buffer)
+                .with_context(|| "AIList index ended unexpectedly")?;
+            Ok(buffer
+                .chunks_exact(4)
+                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect())
+        };
+        // how many items of `size` bytes a length read from the stream may claim
+        let check_length = |reader: &mut R, length: u64, size: u64| -> Result<usize> {
+            match length.checked_mul(size) {
+                Some(bytes) if bytes <= remaining_bytes(reader)? => Ok(length as usize),
+                _ => anyhow::bail!("AIList index is corrupt: a length runs past the end"),
+            }
+        };
+
+        let n_components = read_u64(reader)?;
+        let n_components = check_length(reader, n_components, 8)?;
+        let mut header_list: Vec<usize> = Vec::with_capacity(n_components);
+        for _ in 0..n_components {
+            header_list.push(read_u64(reader)? as usize);
+        }
+
+        // each interval has a start, end, and max end (u32), and an id (u64)
+        let n = read_u64(reader)?;
+        let n = check_length(reader, n, 20)?;
+        let starts = read_u32s(reader, n)?;
+        let ends = read_u32s(reader, n)?;
+        let max_ends = read_u32s(reader, n)?;
+        let mut ids: Vec<usize> = Vec::with_capacity(n);
+        for _ in 0..n {
+            ids.push(read_u64(reader)? as usize);
+        }
+
+        // components are consecutive, non-overlapping slices of the interval arrays
+        let valid_components = header_list.first() == Some(&0)
+            && header_list.windows(2).all(|window| window[0] <= window[1])
+            && header_list.last().is_some_and(|&offset| offset <= n);
+        if !valid_components {
+            anyhow::bail!("AIList index is corrupt: invalid component offsets")
+        }
+
+        Ok(AIList {
+            starts,
+            ends,
+            max_ends,
+            ids,
+            header_list,
+        })
+    }
+
+    ///
+    /// Iterate over the `(starts, ends, max_ends, ids)` slices of each decomposed component.
+    fn components(&self) -> impl Iterator<Item = (&[u32], &[u32], &[u32], &[usize])> {
+        (0..self.header_list.len()).map(move |i| {
+            let lo = self.header_list[i];
+            let hi = match self.header_list.get(i + 1) {
+                Some(&hi) => hi,
+                None => self.starts.len(),
+            };
+            (
+                &self.starts[lo..hi],
+                &self.ends[lo..hi],
+                &self.max_ends[lo..hi],
+                &self.ids[lo..hi],
+            )
+        })
+    }
+
+    fn count_slice(interval: &Interval, starts: &[u32], ends: &[u32], max_ends: &[u32]) -> usize {
+        let mut count = 0;
+        let mut i = starts.partition_point(|&x| x < interval.end);
+
+        while i > 0 {
+            i -= 1;
+            if interval.start > ends[i] {
+                if interval.start > max_ends[i] {
+                    break;
+                }
+            } else {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn any_slice(interval: &Interval, starts: &[u32], ends: &[u32], max_ends: &[u32]) -> bool {
+        let mut i = starts.partition_point(|&x| x < interval.end);
+
+        while i > 0 {
+            i -= 1;
+            if interval.start > ends[i] {
+                if interval.start > max_ends[i] {
+                    return false;
+                }
+            } else {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+///
+/// One round of AIList decomposition: the intervals kept in this component,
+/// and the heavily-nested ones left over for the next round.
+struct Decomposition {
+    starts: Vec<u32>,
+    ends: Vec<u32>,
+    max_ends: Vec<u32>,
+    ids: Vec<usize>,
+    remaining: Vec<Interval>,
+    remaining_ids: Vec<usize>,
+}
+
+///
+/// Sort and merge `(start, end)` pairs, joining overlapping and book-ended ones.
+fn merge_bounds(mut bounds: Vec<(u32, u32)>) -> Vec<Interval> {
+    bounds.sort_unstable();
+
+    let mut merged: Vec<Interval> = Vec::new();
+    for (start, end) in bounds {
+        match merged.last_mut() {
+            Some(last) if start <= last.end => {
+                last.end = last.end.max(end);
+            }
+            _ => merged.push(Interval { start, end }),
+        }
+    }
+
+    merged
+}
+
+///
+/// The parts of `interval` covered by `index`, clipped to `interval` and merged.
+fn covered_parts(interval: &Interval, index: &AIList) -> Vec<Interval> {
+    let clipped: Vec<(u32, u32)> = index
+        .query(interval)
+        .into_iter()
+        .map(|hit| (hit.start.max(interval.start), hit.end.min(interval.end)))
+        .filter(|(start, end)| start < end)
+        .collect();
+
+    merge_bounds(clipped)
+}
+
+///
+/// Intersect a set of intervals with an index (A∩B). Each interval in `a` is clipped to
+/// the parts covered by `b`, like bedtools `intersect`.
+///
+/// # Arguments
+/// - a: the intervals to intersect
+/// - b: the index to intersect them with
+///
+/// # Returns
+/// - the covered pieces of each interval in `a`, in the order of `a`
+pub fn intersect(a: &[Interval], b: &AIList) -> Vec<Interval> {
+    a.iter()
+        .flat_map(|interval| covered_parts(interval, b))
+        .collect()
+}
+
+///
+/// Subtract an index from a set of intervals (A∖B), like bedtools `subtract`.
+///
+/// # Arguments
+/// - a: the intervals to subtract from
+/// - b: the index to subtract
+///
+/// # Returns
+/// - the uncovered pieces of each interval in `a`, in the order of `a`
+pub fn subtract(a: &[Interval], b: &AIList) -> Vec<Interval> {
+    let mut result: Vec<Interval> = Vec::new();
+
+    for interval in a.iter() {
+        let mut cursor = interval.start;
+        for covered in covered_parts(interval, b) {
+            if covered.start > cursor {
+                result.push(Interval {
+                    start: cursor,
+                    end: covered.start,
+                });
+            }
+            cursor = covered.end;
+        }
+        if cursor < interval.end {
+            result.push(Interval {
+                start: cursor,
+                end: interval.end,
+            });
+        }
+    }
+
+    result
+}
+
+///
+/// Union two sets of intervals (A∪B), merging anything that overlaps or touches.
+///
+/// # Returns
+/// - the merged intervals, sorted by start
+pub fn union(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+    merge_bounds(
+        a.iter()
+            .chain(b.iter())
+            .map(|interval| (interval.start, interval.end))
+            .collect(),
+    )
 }
 
 impl fmt::Display for AIList {
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+
+use crate::ailist::consts::{
+    AILIST_FORMAT_VERSION, DEFAULT_MINIMUM_COVERAGE_LENGTH, MULTI_CHROM_AILIST_HEADER,
+};
+use crate::ailist::{meets_min_overlap, remaining_bytes, AIList, Interval};
+use crate::common::models::{Region, RegionSet};
+use crate::common::utils::extract_regions_from_bed_file;
+
+///
+/// A collection of AILists, one per chromosome, so that genomic regions can be
+/// queried directly instead of partitioning them by chromosome by hand.
+pub struct MultiChromOverlapper {
+    index: HashMap<String, AIList>,
+}
+
+impl MultiChromOverlapper {
+    ///
+    /// Build an overlapper from a set of regions. Regions are partitioned by chromosome
+    /// and each chromosome's AIList is built in parallel. Ids returned by `query_ids`
+    /// are indices into `regions`.
+    ///
+    /// # Arguments
+    /// - regions: the regions to index
+    /// - minimum_coverage_length: passed on to `AIList::new` for every chromosome
+    pub fn from_regions(regions: &[Region], minimum_coverage_length: usize) -> Self {
+        let mut intervals: HashMap<String, (Vec<Interval>, Vec<usize>)> = HashMap::new();
+
+        for (i, region) in regions.iter().enumerate() {
+            let (chr_intervals, chr_ids) = intervals.entry(region.chr.to_owned()).or_default();
+            chr_intervals.push(Interval {
+                start: region.start,
+                end: region.end,
+            });
+            chr_ids.push(i);
+        }
+
+        let index = intervals
+            .into_par_iter()
+            .map(|(chr, (mut chr_intervals, chr_ids))| {
+                let mut ailist = AIList::new(&mut chr_intervals, minimum_coverage_length);
+                // map per-chromosome ids back to positions in `regions`
+                for id in ailist.ids.iter_mut() {
+                    *id = chr_ids[*id];
+                }
+                (chr, ailist)
+            })
+            .collect();
+
+        MultiChromOverlapper { index }
+    }
+
+    ///
+    /// Build an overlapper from a region set.
+    ///
+    /// # Arguments
+    /// - region_set: the region set to index
+    /// - minimum_coverage_length: passed on to `AIList::new` for every chromosome
+    pub fn from_region_set(region_set: &RegionSet, minimum_coverage_length: usize) -> Self {
+        Self::from_regions(&region_set.regions, minimum_coverage_length)
+    }
+
+    ///
+    /// Build an overlapper from a (possibly gzipped) BED file.
+    ///
+    /// # Arguments
+    /// - path: the path to the BED file
+    /// - minimum_coverage_length: passed on to `AIList::new` for every chromosome
+    pub fn from_bed_path(path: &Path, minimum_coverage_length: usize) -> Result<Self> {
+        let regions = extract_regions_from_bed_file(path)?;
+        Ok(Self::from_regions(&regions, minimum_coverage_length))
+    }
+
+    ///
+    /// Save every chromosome's index to a single file so large universes only need to be indexed once.
+    ///
+    /// # Arguments
+    /// - path: the file to write the index to
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| "Failed to create index file!")?;
+        let mut writer = BufWriter::new(file);
+
+        writer
+            .write_all(MULTI_CHROM_AILIST_HEADER)
+            .with_context(|| "Failed to write index header to file!")?;
+        writer
+            .write_all(&AILIST_FORMAT_VERSION.to_le_bytes())
+            .with_context(|| "Failed to write index format version to file!")?;
+        writer.write_all(&(self.index.len() as u64).to_le_bytes())?;
+
+        for (chr, ailist) in self.index.iter() {
+            writer.write_all(&(chr.len() as u32).to_le_bytes())?;
+            writer.write_all(chr.as_bytes())?;
+            ailist.write_to(&mut writer)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    ///
+    /// Load an index previously written with `save`.
+    ///
+    /// # Arguments
+    /// - path: the file to read the index from
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| "Failed to open index file!")?;
+        let mut reader = BufReader::new(file);
+
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        if &header != MULTI_CHROM_AILIST_HEADER {
+            anyhow::bail!("File doesn't appear to be a valid multi-chromosome AIList index.")
+        }
+
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != AILIST_FORMAT_VERSION {
+            anyhow::bail!("Unsupported index format version: {}", version[0])
+        }
+
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+
+        let mut index: HashMap<String, AIList> = HashMap::new();
+        for _ in 0..count {
+            let mut name_len = [0; 4];
+            reader.read_exact(&mut name_len)?;
+            let name_len = u32::from_le_bytes(name_len) as u64;
+            if name_len > remaining_bytes(&mut reader)? {
+                anyhow::bail!("Index is corrupt: a chromosome name runs past the end of the file")
+            }
+            let mut name = vec![0; name_len as usize];
+            reader.read_exact(&mut name)?;
+            let chr =
+                String::from_utf8(name).with_context(|| "Invalid chromosome name in index")?;
+
+            let ailist = AIList::read_from(&mut reader)
+                .with_context(|| format!("Failed to read index for {}", chr))?;
+            index.insert(chr, ailist);
+        }
+
+        Ok(MultiChromOverlapper { index })
+    }
+
+    ///
+    /// Query for intervals overlapping a region. Regions on chromosomes that
+    /// aren't in the index have no overlaps.
+    pub fn query(&self, region: &Region) -> Vec<Interval> {
+        match self.index.get(&region.chr) {
+            Some(ailist) => ailist.query(&Interval {
+                start: region.start,
+                end: region.end,
+            }),
+            None => Vec::new(),
+        }
+    }
+
+    ///
+    /// Query for the ids of regions overlapping a region. Ids are indices into the
+    /// regions the overlapper was built from.
+    pub fn query_ids(&self, region: &Region) -> Vec<usize> {
+        match self.index.get(&region.chr) {
+            Some(ailist) => ailist.query_ids(&Interval {
+                start: region.start,
+                end: region.end,
+            }),
+            None => Vec::new(),
+        }
+    }
+
+    ///
+    /// Query many regions at once.
+    ///
+    /// # Returns
+    /// - the overlapping intervals for each region, in the same order as `regions`
+    pub fn query_batch(&self, regions: &[Region]) -> Vec<Vec<Interval>> {
+        regions.iter().map(|region| self.query(region)).collect()
+    }
+
+    ///
+    /// Query many regions at once, spreading the work across threads with rayon.
+    ///
+    /// # Returns
+    /// - the overlapping intervals for each region, in the same order as `regions`
+    pub fn par_query_batch(&self, regions: &[Region]) -> Vec<Vec<Interval>> {
+        regions
+            .par_iter()
+            .map(|region| self.query(region))
+            .collect()
+    }
+
+    ///
+    /// Count the intervals overlapping a region. Like every query on the index, ends are
+    /// closed, so intervals that end exactly at the region's start are counted.
+    pub fn count(&self, region: &Region) -> usize {
+        match self.index.get(&region.chr) {
+            Some(ailist) => ailist.count(&Interval {
+                start: region.start,
+                end: region.end,
+            }),
+            None => 0,
+        }
+    }
+
+    ///
+    /// Check whether any interval overlaps a region. Intervals that end exactly at the
+    /// region's start count as overlapping.
+    pub fn any(&self, region: &Region) -> bool {
+        match self.index.get(&region.chr) {
+            Some(ailist) => ailist.any(&Interval {
+                start: region.start,
+                end: region.end,
+            }),
+            None => false,
+        }
+    }
+
+    ///
+    /// Count the intervals overlapping fixed-size windows tiled across a chromosome.
+    /// See `AIList::window_counts`, including its closed interval ends. Chromosomes that
+    /// aren't in the index get all zeros.
+    ///
+    /// # Arguments
+    /// - chr: the chromosome to tile
+    /// - length: the length of the chromosome
+    /// - size: the window size
+    /// - step: the distance between window starts
+    pub fn window_counts(
+        &self,
+        chr: &str,
+        length: u32,
+        size: u32,
+        step: u32,
+    ) -> Result<Vec<usize>> {
+        match self.index.get(chr) {
+            Some(ailist) => ailist.window_counts(length, size, step),
+            None => Ok(vec![0; AIList::num_windows(length, size, step)?]),
+        }
+    }
+
+    ///
+    /// Get the AIList for a single chromosome.
+    pub fn get(&self, chr: &str) -> Option<&AIList> {
+        self.index.get(chr)
+    }
+
+    ///
+    /// The chromosomes present in the index, in no particular order.
+    pub fn chromosomes(&self) -> impl Iterator<Item = &String> {
+        self.index.keys()
+    }
+}
+
+///
+/// Annotate regions with the labels of the labeled regions they overlap, e.g. peaks
+/// with the names of the genes they fall in. Overlap criteria follow
+/// `AIList::query_with_min_overlap`: a `fraction` of 0 accepts any overlap.
+///
+/// # Arguments
+/// - regions: the regions to annotate
+/// - labeled: the labeled regions to match against
+/// - fraction: the minimum fraction of each region (between 0 and 1) that must be overlapped
+/// - reciprocal: whether the fraction must also hold for the labeled region
+///
+/// # Returns
+/// - each region with the labels of its matches, in the same order as `regions`;
+///   labels are in the order of `labeled`
+pub fn annotate<L: Clone>(
+    regions: &[Region],
+    labeled: &[(Region, L)],
+    fraction: f64,
+    reciprocal: bool,
+) -> Vec<(Region, Vec<L>)> {
+    let labeled_regions: Vec<Region> = labeled.iter().map(|(r, _)| r.to_owned()).collect();
+    let overlapper =
+        MultiChromOverlapper::from_regions(&labeled_regions, DEFAULT_MINIMUM_COVERAGE_LENGTH);
+
+    regions
+        .iter()
+        .map(|region| {
+            // keep labels in the order they were given
+            let mut ids = overlapper.query_ids(region);
+            ids.sort_unstable();
+
+            let labels = ids
+                .into_iter()
+                .filter(|&id| {
+                    let hit = &labeled[id].0;
+                    meets_min_overlap(
+                        (region.start, region.end),
+                        (hit.start, hit.end),
+                        fraction,
+                        reciprocal,
+                    )
+                })
+                .map(|id| labeled[id].1.clone())
+                .collect();
+
+            (region.to_owned(), labels)
+        })
+        .collect()
+}
@@ -1,3 +1,4 @@
 pub mod consts;
 pub mod models;
+pub mod sweep;
 pub mod utils;
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use crate::common::models::{Region, RegionSet, Strand};
+use crate::common::utils::get_dynamic_reader;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum AnnotationFormat {
+    /// attributes look like `gene_id "ENSG0001"; gene_name "ABC";`
+    Gtf,
+    /// attributes look like `ID=gene0001;Name=ABC`
+    Gff3,
+}
+
+impl AnnotationFormat {
+    ///
+    /// Guess the format from a file name, looking past a trailing `.gz`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+
+        if name.ends_with(".gtf") {
+            Some(AnnotationFormat::Gtf)
+        } else if name.ends_with(".gff3") || name.ends_with(".gff") {
+            Some(AnnotationFormat::Gff3)
+        } else {
+            None
+        }
+    }
+}
+
+///
+/// One feature line of a GTF or GFF3 file. The coordinates are converted from the
+/// 1-based, closed intervals of the file to the 0-based, half-open intervals used for
+/// every other `Region`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct AnnotationRecord {
+    pub region: Region,
+    pub source: String,
+    /// the feature type, e.g. `gene`, `transcript`, or `exon`
+    pub feature: String,
+    pub score: Option<f64>,
+    pub strand: Strand,
+    pub frame: Option<u8>,
+    pub attributes: HashMap<String, String>,
+}
+
+impl AnnotationRecord {
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(|value| value.as_str())
+    }
+
+    ///
+    /// Parse a single feature line.
+    ///
+    /// # Arguments
+    /// - line: the tab separated line, with all nine columns
+    /// - format: how to read the attribute column
+    pub fn parse(line: &str, format: AnnotationFormat) -> Result<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 9 {
+            anyhow::bail!("Annotation line does not have 9 fields: {}", line);
+        }
+
+        let parse_position = |field: &str| -> Result<u32> {
+            field
+                .parse::<u32>()
+                .with_context(|| format!("Failed to parse position in annotation line: {}", line))
+        };
+
+        let start = parse_position(fields[3])?;
+        let end = parse_position(fields[4])?;
+        if start == 0 || end < start {
+            anyhow::bail!("Invalid feature coordinates in annotation line: {}", line);
+        }
+
+        let attributes = match format {
+            AnnotationFormat::Gtf => parse_gtf_attributes(fields[8]),
+            AnnotationFormat::Gff3 => parse_gff3_attributes(fields[8]),
+        };
+
+        Ok(AnnotationRecord {
+            region: Region {
+                chr: fields[0].to_string(),
+                start: start - 1,
+                end,
+            },
+            source: fields[1].to_string(),
+            feature: fields[2].to_string(),
+            score: optional(fields[5])
+                .map(|f| {
+                    f.parse::<f64>().with_context(|| {
+                        format!("Failed to parse score in annotation line: {}", line)
+                    })
+                })
+                .transpose()?,
+            strand: match optional(fields[6]) {
+                Some("?") | None => Strand::Unknown,
+                Some(strand) => Strand::from_str(strand)?,
+            },
+            frame: optional(fields[7])
+                .map(|f| {
+                    f.parse::<u8>().with_context(|| {
+                        format!("Failed to parse frame in annotation line: {}", line)
+                    })
+                })
+                .transpose()?,
+            attributes,
+        })
+    }
+}
+
+/// `.` marks an empty column
+fn optional(field: &str) -> Option<&str> {
+    Some(field).filter(|f| *f != "." && !f.is_empty())
+}
+
+fn parse_gtf_attributes(field: &str) -> HashMap<String, String> {
+    field
+        .split(';')
+        .filter_map(|attribute| {
+            let (key, value) = attribute.trim().split_once(' ')?;
+            Some((key.to_string(), value.trim().trim_matches('"').to_string()))
+        })
+        .collect()
+}
+
+fn parse_gff3_attributes(field: &str) -> HashMap<String, String> {
+    field
+        .split(';')
+        .filter_map(|attribute| {
+            let (key, value) = attribute.trim().split_once('=')?;
+            Some((percent_decode(key), percent_decode(value)))
+        })
+        .collect()
+}
+
+/// GFF3 escapes its reserved characters (`;`, `=`, `&`, `,`, tabs) as `%XX`.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+///
+/// Read the feature lines of a (possibly gzipped) GTF or GFF3 file. Comment and
+/// directive lines are skipped, as is any embedded `##FASTA` section.
+///
+/// # Arguments
+/// - path: the path to the annotation file
+/// - format: the attribute format, or `None` to guess it from the file name
+/// - feature_types: only keep these feature types (e.g. `["gene", "exon"]`), or all if empty
+pub fn extract_annotations_from_file(
+    path: &Path,
+    format: Option<AnnotationFormat>,
+    feature_types: &[&str],
+) -> Result<Vec<AnnotationRecord>> {
+    let format = match format.or_else(|| AnnotationFormat::from_path(path)) {
+        Some(format) => format,
+        None => anyhow::bail!(
+            "Can't tell whether {} is a GTF or GFF3 file",
+            path.display()
+        ),
+    };
+
+    let reader = get_dynamic_reader(path)?;
+
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let line = line.with_context(|| "Failed parsing line in annotation file")?;
+        if line.starts_with("##FASTA") {
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // check the feature type before parsing the rest of the line
+        let feature = line.split('\t').nth(2).unwrap_or_default();
+        if !feature_types.is_empty() && !feature_types.contains(&feature) {
+            continue;
+        }
+
+        records.push(AnnotationRecord::parse(&line, format)?);
+    }
+
+    Ok(records)
+}
+
+impl From<&[AnnotationRecord]> for RegionSet {
+    fn from(value: &[AnnotationRecord]) -> Self {
+        value
+            .iter()
+            .map(|record| record.region.to_owned())
+            .collect::<Vec<Region>>()
+            .into()
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::BufRead;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use crate::common::models::Region;
+use crate::common::utils::get_dynamic_reader;
+
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy, Default)]
+pub enum Strand {
+    Forward,
+    Reverse,
+    #[default]
+    Unknown,
+}
+
+impl FromStr for Strand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "+" => Ok(Strand::Forward),
+            "-" => Ok(Strand::Reverse),
+            "." => Ok(Strand::Unknown),
+            _ => anyhow::bail!("Invalid strand: {}", s),
+        }
+    }
+}
+
+///
+/// A BED record with any of the standard columns, from BED3 up to BED12. Columns the
+/// line doesn't have are left as `None` (or `Strand::Unknown`). Columns past the twelfth
+/// are ignored.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BedRecord {
+    pub chr: String,
+    pub start: u32,
+    pub end: u32,
+    pub name: Option<String>,
+    pub score: Option<f64>,
+    pub strand: Strand,
+    pub thick_start: Option<u32>,
+    pub thick_end: Option<u32>,
+    pub item_rgb: Option<String>,
+    /// (start, size) of each block, with starts relative to `start`, as in columns 11 and 12
+    pub blocks: Option<Vec<(u32, u32)>>,
+}
+
+fn parse_list(field: &str, line: &str) -> Result<Vec<u32>> {
+    field
+        .trim_end_matches(',')
+        .split(',')
+        .map(|value| {
+            value
+                .parse::<u32>()
+                .with_context(|| format!("Failed to parse block list in BED file line: {}", line))
+        })
+        .collect()
+}
+
+impl FromStr for BedRecord {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            anyhow::bail!("BED file line does not have at least 3 fields: {}", line);
+        }
+
+        let field = |i: usize| fields.get(i).copied().filter(|f| !f.is_empty());
+        let parse_u32 = |i: usize, column: &str| -> Result<Option<u32>> {
+            field(i)
+                .map(|f| {
+                    f.parse::<u32>().with_context(|| {
+                        format!("Failed to parse {} in BED file line: {}", column, line)
+                    })
+                })
+                .transpose()
+        };
+
+        let required = |i: usize, column: &str| -> Result<u32> {
+            parse_u32(i, column)?
+                .with_context(|| format!("Failed to parse {} in BED file line: {}", column, line))
+        };
+
+        let start = required(1, "start position")?;
+        let end = required(2, "end position")?;
+        if end < start {
+            anyhow::bail!("BED file line ends before it starts: {}", line);
+        }
+
+        let blocks = match (field(9), field(10), field(11)) {
+            (None, None, None) => None,
+            (Some(count), Some(sizes), Some(starts)) => {
+                let count = count.parse::<usize>().with_context(|| {
+                    format!("Failed to parse block count in BED file line: {}", line)
+                })?;
+                let sizes = parse_list(sizes, line)?;
+                let starts = parse_list(starts, line)?;
+                if sizes.len() != count || starts.len() != count {
+                    anyhow::bail!("Block lists don't match the block count: {}", line);
+                }
+
+                let blocks: Vec<(u32, u32)> = starts.into_iter().zip(sizes).collect();
+                for (block_start, size) in blocks.iter() {
+                    let block_end = start
+                        .checked_add(*block_start)
+                        .and_then(|block_start| block_start.checked_add(*size));
+                    if !matches!(block_end, Some(block_end) if block_end <= end) {
+                        anyhow::bail!("A block extends past the end of the record: {}", line);
+                    }
+                }
+                Some(blocks)
+            }
+            _ => anyhow::bail!("BED12 line is missing some of its block columns: {}", line),
+        };
+
+        Ok(BedRecord {
+            chr: fields[0].to_string(),
+            start,
+            end,
+            name: field(3).map(String::from),
+            score: field(4)
+                .map(|f| {
+                    f.parse::<f64>().with_context(|| {
+                        format!("Failed to parse score in BED file line: {}", line)
+                    })
+                })
+                .transpose()?,
+            strand: field(5)
+                .map(Strand::from_str)
+                .transpose()?
+                .unwrap_or_default(),
+            thick_start: parse_u32(6, "thick start")?,
+            thick_end: parse_u32(7, "thick end")?,
+            item_rgb: field(8).map(String::from),
+            blocks,
+        })
+    }
+}
+
+impl From<&BedRecord> for Region {
+    fn from(value: &BedRecord) -> Self {
+        Region {
+            chr: value.chr.to_owned(),
+            start: value.start,
+            end: value.end,
+        }
+    }
+}
+
+impl BedRecord {
+    pub fn region(&self) -> Region {
+        self.into()
+    }
+
+    ///
+    /// The blocks (e.g. exons) of the record as regions, in order. A record without block
+    /// columns is a single block spanning the whole record. Records parsed from a line have
+    /// their blocks checked already; this only fails if `blocks` was changed afterwards.
+    pub fn blocks(&self) -> Result<Vec<Region>> {
+        let Some(blocks) = &self.blocks else {
+            return Ok(vec![self.region()]);
+        };
+
+        blocks
+            .iter()
+            .map(|(block_start, size)| {
+                let start = self.start.checked_add(*block_start);
+                let end = start.and_then(|start| start.checked_add(*size));
+                match (start, end) {
+                    (Some(start), Some(end)) => Ok(Region {
+                        chr: self.chr.to_owned(),
+                        start,
+                        end,
+                    }),
+                    _ => anyhow::bail!(
+                        "Block ({}, {}) of the record at {}:{} is out of range",
+                        block_start,
+                        size,
+                        self.chr,
+                        self.start
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    ///
+    /// The gaps between consecutive blocks (e.g. introns), in order.
+    pub fn gaps(&self) -> Result<Vec<Region>> {
+        Ok(self
+            .blocks()?
+            .windows(2)
+            .filter(|pair| pair[0].end < pair[1].start)
+            .map(|pair| Region {
+                chr: self.chr.to_owned(),
+                start: pair[0].end,
+                end: pair[1].start,
+            })
+            .collect())
+    }
+
+    ///
+    /// The thick (e.g. coding) part of the record, if it has a non-empty one.
+    pub fn thick_region(&self) -> Option<Region> {
+        let start = self.thick_start?;
+        let end = self.thick_end?;
+
+        (start < end).then(|| Region {
+            chr: self.chr.to_owned(),
+            start,
+            end,
+        })
+    }
+
+    ///
+    /// The parts of the blocks inside the thick region (e.g. the coding parts of exons).
+    pub fn thick_blocks(&self) -> Result<Vec<Region>> {
+        let Some(thick) = self.thick_region() else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self
+            .blocks()?
+            .into_iter()
+            .filter_map(|block| {
+                let start = block.start.max(thick.start);
+                let end = block.end.min(thick.end);
+                (start < end).then_some(Region {
+                    start,
+                    end,
+                    ..block
+                })
+            })
+            .collect())
+    }
+}
+
+///
+/// Read every record of a (possibly gzipped) BED file with all of its standard columns.
+/// Empty lines and `#`, `track`, and `browser` header lines are skipped.
+///
+/// # Arguments
+/// - path: the path to the BED file
+pub fn extract_bed_records_from_file(path: &Path) -> Result<Vec<BedRecord>> {
+    let reader = get_dynamic_reader(path)?;
+
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let line = line.with_context(|| "Failed parsing line in BED file")?;
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+        records.push(line.parse::<BedRecord>()?);
+    }
+
+    Ok(records)
+}
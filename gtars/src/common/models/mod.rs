@@ -1,12 +1,18 @@
+pub mod annotation;
+pub mod bed_record;
 pub mod region;
 pub mod region_set;
 pub mod tokenized_region;
 pub mod tokenized_regionset;
 pub mod universe;
+pub mod variant;
 
 // re-export for cleaner imports
+pub use self::annotation::{AnnotationFormat, AnnotationRecord};
+pub use self::bed_record::{BedRecord, Strand};
 pub use self::region::Region;
 pub use self::region_set::RegionSet;
 pub use self::tokenized_region::TokenizedRegion;
 pub use self::tokenized_regionset::TokenizedRegionSet;
 pub use self::universe::Universe;
+pub use self::variant::VariantRecord;
@@ -1,9 +1,10 @@
-use crate::common::utils::extract_regions_from_bed_file;
+use crate::common::utils::{extract_regions_from_bed_file, merge_regions_with};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 use anyhow::Result;
+use md5::{Digest, Md5};
 
 use crate::common::models::Region;
 
@@ -93,6 +94,52 @@ impl RegionSet {
         Ok(())
     }
 
+    ///
+    /// Compute the BEDbase identifier for this region set without touching disk.
+    ///
+    /// The chromosome, start, and end columns are each joined with commas and hashed with md5.
+    /// The three hex digests are then joined with commas and hashed again. Regions are digested
+    /// in their current order, so sort them the same way as the uploaded file to get a matching id.
+    ///
+    /// # Returns
+    /// The identifier as a lowercase hex string
+    pub fn identifier(&self) -> String {
+        let mut chrs: Vec<&str> = Vec::with_capacity(self.regions.len());
+        let mut starts: Vec<String> = Vec::with_capacity(self.regions.len());
+        let mut ends: Vec<String> = Vec::with_capacity(self.regions.len());
+
+        for region in self.regions.iter() {
+            chrs.push(&region.chr);
+            starts.push(region.start.to_string());
+            ends.push(region.end.to_string());
+        }
+
+        let chrom_digest = Md5::digest(chrs.join(","));
+        let start_digest = Md5::digest(starts.join(","));
+        let end_digest = Md5::digest(ends.join(","));
+
+        let combined = format!("{:x},{:x},{:x}", chrom_digest, start_digest, end_digest);
+
+        format!("{:x}", Md5::digest(combined))
+    }
+
+    ///
+    /// Merge overlapping or nearby regions into a new, sorted region set.
+    ///
+    /// # Arguments
+    /// - max_distance: the largest gap (in bases) between two regions that still gets merged
+    pub fn merge(&self, max_distance: u32) -> RegionSet {
+        let regions = self.regions.iter().map(|r| (r.clone(), ())).collect();
+        let merged = merge_regions_with(regions, max_distance, |_, _| ());
+
+        RegionSet::from(
+            merged
+                .into_iter()
+                .map(|(region, _)| region)
+                .collect::<Vec<Region>>(),
+        )
+    }
+
     pub fn len(&self) -> usize {
         self.regions.len()
     }
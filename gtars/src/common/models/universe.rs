@@ -1,12 +1,20 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use rayon::prelude::*;
 
+use crate::ailist::consts::DEFAULT_MINIMUM_COVERAGE_LENGTH;
+use crate::ailist::MultiChromOverlapper;
 use crate::common::models::region::Region;
+use crate::common::models::region_set::RegionSet;
 use crate::common::utils::{
     extract_regions_from_bed_file, generate_id_to_region_map, generate_region_to_id_map,
+    read_chrom_sizes, stream_regions_from_bed_file,
 };
+use crate::io::{read_gtok_header, read_tokens_from_gtok};
 
 #[derive(Clone, Eq, PartialEq)]
 pub struct Universe {
@@ -47,6 +55,175 @@ impl Universe {
     pub fn is_empty(&self) -> bool {
         self.region_to_id.len() == 0
     }
+
+    ///
+    /// A digest that identifies this universe, stored in `.gtok` headers so token files can be
+    /// matched with the universe they were written with. This is the BEDbase identifier of the
+    /// universe's regions in id order, see `RegionSet::identifier`.
+    pub fn digest(&self) -> String {
+        RegionSet::from(self.regions.clone()).identifier()
+    }
+
+    ///
+    /// Count how often each region is hit across a corpus of `.bed`, `.bed.gz`, and `.gtok` files,
+    /// and drop the regions hit fewer than `min_count` times. Bed files are overlapped with the
+    /// universe; `.gtok` files are assumed to hold token ids from this universe, and files whose
+    /// header records a different universe digest are rejected. Files are counted in parallel.
+    /// Special tokens added with `insert_token` are always kept, after the remaining regions.
+    ///
+    /// # Arguments
+    /// - data_dir: the directory holding the corpus
+    /// - min_count: the minimum number of hits a region needs to be kept
+    ///
+    /// # Returns
+    /// - the pruned universe, and the dropped regions with their counts
+    pub fn prune_by_count(
+        &self,
+        data_dir: &Path,
+        min_count: u64,
+    ) -> Result<(Universe, Vec<(Region, u64)>)> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        for entry in read_dir(data_dir)
+            .with_context(|| format!("Failed to read directory {}", data_dir.display()))?
+        {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.ends_with(".bed") || name.ends_with(".bed.gz") || name.ends_with(".gtok") {
+                files.push(path);
+            }
+        }
+
+        let overlapper =
+            MultiChromOverlapper::from_regions(&self.regions, DEFAULT_MINIMUM_COVERAGE_LENGTH);
+        let digest = self.digest();
+
+        let counts = files
+            .par_iter()
+            .map(|path| self.count_file(path, &overlapper, &digest))
+            .try_reduce(
+                || vec![0; self.len()],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    Ok(a)
+                },
+            )?;
+
+        let mut kept: Vec<Region> = Vec::new();
+        let mut dropped: Vec<(Region, u64)> = Vec::new();
+        let mut seen: HashSet<&Region> = HashSet::new();
+        for region in self.regions.iter() {
+            // a region listed twice has one id, so it's kept or dropped once
+            if !seen.insert(region) {
+                continue;
+            }
+            let count = counts[self.region_to_id[region] as usize];
+            if count >= min_count {
+                kept.push(region.to_owned());
+            } else {
+                dropped.push((region.to_owned(), count));
+            }
+        }
+
+        // special tokens are the ids whose region isn't in `regions`. ids aren't positions in
+        // `regions` when it has duplicates, so they're found by region and added back in id order
+        let mut specials: Vec<(&u32, &Region)> = self
+            .id_to_region
+            .iter()
+            .filter(|(_, region)| !seen.contains(region))
+            .collect();
+        specials.sort_unstable_by_key(|(id, _)| **id);
+
+        let mut pruned = Universe::from(kept);
+        for (_, token) in specials {
+            pruned.insert_token(token);
+        }
+
+        Ok((pruned, dropped))
+    }
+
+    ///
+    /// Build a universe of fixed-size tiles across a genome, for bin-based tokenization
+    /// when no peak universe is available. Tiles start at 0 and every `step` bases after
+    /// that; the last tile on each chromosome is clipped to the chromosome end.
+    ///
+    /// # Arguments
+    /// - chrom_sizes: each chromosome's name and length, in the order tiles should get ids
+    /// - size: the tile size
+    /// - step: the distance between tile starts, defaults to `size` (non-overlapping tiles)
+    pub fn from_tiles(chrom_sizes: &[(String, u32)], size: u32, step: Option<u32>) -> Result<Self> {
+        let step = step.unwrap_or(size);
+        if size == 0 || step == 0 {
+            anyhow::bail!("Tile size and step must be positive.");
+        }
+
+        let mut regions: Vec<Region> = Vec::new();
+        for (chr, length) in chrom_sizes.iter() {
+            for start in (0..*length).step_by(step as usize) {
+                regions.push(Region {
+                    chr: chr.to_owned(),
+                    start,
+                    end: start.saturating_add(size).min(*length),
+                });
+            }
+        }
+
+        Ok(Universe::from(regions))
+    }
+
+    ///
+    /// Build a universe of fixed-size tiles from a `chrom.sizes` file. See `Universe::from_tiles`.
+    ///
+    /// # Arguments
+    /// - path: the path to the (possibly gzipped) `chrom.sizes` file
+    /// - size: the tile size
+    /// - step: the distance between tile starts, defaults to `size`
+    pub fn from_chrom_sizes(path: &Path, size: u32, step: Option<u32>) -> Result<Self> {
+        let chrom_sizes = read_chrom_sizes(path)?;
+        Self::from_tiles(&chrom_sizes, size, step)
+    }
+
+    fn count_file(
+        &self,
+        path: &Path,
+        overlapper: &MultiChromOverlapper,
+        digest: &str,
+    ) -> Result<Vec<u64>> {
+        let mut counts: Vec<u64> = vec![0; self.len()];
+
+        if path.extension() == Some(OsStr::new("gtok")) {
+            let filename = path
+                .to_str()
+                .with_context(|| format!("{} is not a valid utf-8 path", path.display()))?;
+            let header = read_gtok_header(filename)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            header.check_universe_digest(digest).with_context(|| {
+                format!("{} was tokenized with a different universe", path.display())
+            })?;
+
+            let ids = read_tokens_from_gtok(filename)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            for id in ids {
+                // ids past the end of the universe can't come from it
+                if let Some(count) = counts.get_mut(id as usize) {
+                    *count += 1;
+                }
+            }
+        } else {
+            for region in stream_regions_from_bed_file(path)? {
+                let region =
+                    region.with_context(|| format!("Failed to read {}", path.display()))?;
+                for hit in overlapper.query_ids(&region) {
+                    // the index treats ends as closed, bed is half-open
+                    let hit = &self.regions[hit];
+                    if hit.end > region.start {
+                        counts[self.region_to_id[hit] as usize] += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(counts)
+    }
 }
 
 impl From<Vec<Region>> for Universe {
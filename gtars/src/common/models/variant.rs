@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::common::models::{Region, RegionSet};
+use crate::common::utils::get_dynamic_reader;
+
+///
+/// One record of a VCF file. The region covers the reference allele, converted from
+/// the 1-based position of the file to a 0-based, half-open interval, and widened by
+/// any padding it was read with.
+#[derive(PartialEq, Debug, Clone)]
+pub struct VariantRecord {
+    pub region: Region,
+    pub id: Option<String>,
+    pub reference: String,
+    pub alternates: Vec<String>,
+    /// the INFO fields that were asked for; flags have an empty value
+    pub info: HashMap<String, String>,
+}
+
+impl VariantRecord {
+    ///
+    /// Parse a single VCF data line.
+    ///
+    /// # Arguments
+    /// - line: the tab separated line, with at least the first eight columns
+    /// - padding: the number of bases to widen the region by on each side
+    /// - info_fields: the INFO keys to keep, or all of them if empty
+    pub fn parse(line: &str, padding: u32, info_fields: &[&str]) -> Result<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 8 {
+            anyhow::bail!("VCF line does not have at least 8 fields: {}", line);
+        }
+
+        let position = fields[1]
+            .parse::<u32>()
+            .with_context(|| format!("Failed to parse position in VCF line: {}", line))?;
+        if position == 0 {
+            anyhow::bail!("Invalid position in VCF line: {}", line);
+        }
+
+        let reference = fields[3].to_string();
+        let start = position - 1;
+        // a long enough allele near the end of the coordinate space doesn't fit in a region
+        let end = u32::try_from(reference.len().max(1))
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .with_context(|| {
+                format!(
+                    "Reference allele ends past the largest position in VCF line: {}",
+                    line
+                )
+            })?;
+
+        let info = fields[7]
+            .split(';')
+            .filter(|field| !field.is_empty() && *field != ".")
+            .map(|field| match field.split_once('=') {
+                Some((key, value)) => (key, value),
+                None => (field, ""),
+            })
+            .filter(|(key, _)| info_fields.is_empty() || info_fields.contains(key))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        Ok(VariantRecord {
+            region: Region {
+                chr: fields[0].to_string(),
+                start: start.saturating_sub(padding),
+                end: end.saturating_add(padding),
+            },
+            id: Some(fields[2]).filter(|id| *id != ".").map(String::from),
+            reference,
+            alternates: fields[4]
+                .split(',')
+                .filter(|alt| *alt != ".")
+                .map(String::from)
+                .collect(),
+            info,
+        })
+    }
+}
+
+///
+/// Read the records of a (possibly gzipped) VCF file. Header lines are skipped.
+///
+/// # Arguments
+/// - path: the path to the VCF file
+/// - padding: the number of bases to widen each variant's region by on each side
+/// - info_fields: the INFO keys to keep on each record, or all of them if empty
+pub fn extract_variants_from_file(
+    path: &Path,
+    padding: u32,
+    info_fields: &[&str],
+) -> Result<Vec<VariantRecord>> {
+    let reader = get_dynamic_reader(path)?;
+
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let line = line.with_context(|| "Failed parsing line in VCF file")?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        records.push(VariantRecord::parse(&line, padding, info_fields)?);
+    }
+
+    Ok(records)
+}
+
+impl From<&[VariantRecord]> for RegionSet {
+    fn from(value: &[VariantRecord]) -> Self {
+        value
+            .iter()
+            .map(|record| record.region.to_owned())
+            .collect::<Vec<Region>>()
+            .into()
+    }
+}
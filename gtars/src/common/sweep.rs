@@ -0,0 +1,148 @@
+//! Streaming overlap of two coordinate-sorted region streams.
+//!
+//! This is the bedtools `intersect -sorted` strategy: both inputs are walked once, side by side,
+//! and only the regions of `b` that can still overlap the current region of `a` are kept around.
+//! Memory use is therefore bounded by the overlap depth of `b`, not by the size of either input.
+//!
+//! Both inputs must be sorted by chromosome name (lexicographically) and then by start position,
+//! i.e. the order produced by `sort -k1,1 -k2,2n`.
+use std::collections::VecDeque;
+
+use anyhow::Result;
+
+use crate::common::models::Region;
+
+pub struct SortedOverlaps<A, B>
+where
+    A: Iterator<Item = Result<Region>>,
+    B: Iterator<Item = Result<Region>>,
+{
+    a: A,
+    b: B,
+    next_b: Option<Region>,
+    window: VecDeque<Region>,
+    pending: VecDeque<(Region, Region)>,
+    last_a: Option<Region>,
+    last_b: Option<Region>,
+}
+
+///
+/// Find all overlapping pairs between two sorted region streams in a single pass.
+///
+/// Pairs are yielded in the order of `a`. An error is yielded if either input turns out not to be sorted.
+///
+/// # Arguments
+/// - a: the query regions
+/// - b: the regions to intersect with
+///
+/// # Returns
+/// - an iterator over `(a, b)` pairs that overlap
+pub fn sweep_overlaps<A, B>(a: A, b: B) -> SortedOverlaps<A::IntoIter, B::IntoIter>
+where
+    A: IntoIterator<Item = Result<Region>>,
+    B: IntoIterator<Item = Result<Region>>,
+{
+    SortedOverlaps {
+        a: a.into_iter(),
+        b: b.into_iter(),
+        next_b: None,
+        window: VecDeque::new(),
+        pending: VecDeque::new(),
+        last_a: None,
+        last_b: None,
+    }
+}
+
+fn check_sorted(last: &mut Option<Region>, region: &Region) -> Result<()> {
+    if let Some(prev) = last {
+        if (region.chr.as_str(), region.start) < (prev.chr.as_str(), prev.start) {
+            anyhow::bail!(
+                "Input is not sorted: {}:{}-{} comes after {}:{}-{}",
+                region.chr,
+                region.start,
+                region.end,
+                prev.chr,
+                prev.start,
+                prev.end
+            );
+        }
+    }
+    *last = Some(region.clone());
+
+    Ok(())
+}
+
+impl<A, B> SortedOverlaps<A, B>
+where
+    A: Iterator<Item = Result<Region>>,
+    B: Iterator<Item = Result<Region>>,
+{
+    ///
+    /// Pull regions from `b` until it passes the end of `a`, keeping the ones
+    /// that could overlap `a` (or any region after it) in the window.
+    fn advance_b(&mut self, a: &Region) -> Result<()> {
+        loop {
+            if self.next_b.is_none() {
+                match self.b.next() {
+                    Some(b) => {
+                        let b = b?;
+                        check_sorted(&mut self.last_b, &b)?;
+                        self.next_b = Some(b);
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            let b = self.next_b.as_ref().unwrap();
+            let before_a = b.chr < a.chr || (b.chr == a.chr && b.start < a.end);
+            if !before_a {
+                return Ok(());
+            }
+
+            let b = self.next_b.take().unwrap();
+            // regions on an earlier chromosome, or that end before `a` starts,
+            // can't overlap anything later in `a` either
+            if b.chr == a.chr && b.end > a.start {
+                self.window.push_back(b);
+            }
+        }
+    }
+}
+
+impl<A, B> Iterator for SortedOverlaps<A, B>
+where
+    A: Iterator<Item = Result<Region>>,
+    B: Iterator<Item = Result<Region>>,
+{
+    type Item = Result<(Region, Region)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.pending.pop_front() {
+                return Some(Ok(pair));
+            }
+
+            let a = match self.a.next()? {
+                Ok(a) => a,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if let Err(e) = check_sorted(&mut self.last_a, &a) {
+                return Some(Err(e));
+            }
+
+            // anything that ends before `a` starts is done for good
+            self.window.retain(|b| b.chr == a.chr && b.end > a.start);
+
+            if let Err(e) = self.advance_b(&a) {
+                return Some(Err(e));
+            }
+
+            for b in self.window.iter() {
+                if b.start < a.end {
+                    self.pending.push_back((a.clone(), b.clone()));
+                }
+            }
+        }
+    }
+}
@@ -59,29 +59,147 @@ pub fn extract_regions_from_bed_file(path: &Path) -> Result<Vec<Region>> {
 
     for line in reader.lines() {
         let line = line.with_context(|| "Failed parsing line in BED file")?;
-        let fields: Vec<&str> = line.split('\t').collect();
+        let region = parse_bed_line(&line)?;
 
-        // check length of fields
-        if fields.len() < 3 {
-            anyhow::bail!("BED file line does not have at least 3 fields: {}", line);
+        regions.push(region);
+    }
+
+    Ok(regions)
+}
+
+///
+/// Lazily read regions from a BED file, one line at a time.
+///
+/// Unlike `extract_regions_from_bed_file`, this never holds the whole file in memory,
+/// which makes it suitable for very large (possibly gzipped) files.
+///
+/// # Arguments
+/// - path: the path to the BED file
+///
+/// # Returns
+/// - an iterator over the parsed regions
+pub fn stream_regions_from_bed_file(path: &Path) -> Result<impl Iterator<Item = Result<Region>>> {
+    let reader = get_dynamic_reader(path)?;
+
+    Ok(reader.lines().map(|line| {
+        let line = line.with_context(|| "Failed parsing line in BED file")?;
+        parse_bed_line(&line)
+    }))
+}
+
+///
+/// Read a `chrom.sizes` file: one tab-separated chromosome name and length per line.
+/// Extra columns are ignored.
+///
+/// # Arguments
+/// - path: the path to the (possibly gzipped) file
+///
+/// # Returns
+/// - each chromosome's name and length, in file order
+pub fn read_chrom_sizes(path: &Path) -> Result<Vec<(String, u32)>> {
+    let reader = get_dynamic_reader(path)?;
+
+    let mut chrom_sizes = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
         }
 
-        let chr = fields[0];
-        let start = fields[1].parse::<u32>().with_context(|| {
-            format!("Failed to parse start position in BED file line: {}", line)
-        })?;
-        let end = fields[2]
+        let mut fields = line.split('\t');
+        let chr = fields.next().unwrap_or_default();
+        let length = fields
+            .next()
+            .with_context(|| format!("chrom.sizes line does not have 2 fields: {}", line))?
+            .trim()
             .parse::<u32>()
-            .with_context(|| format!("Failed to parse end position in BED file line: {}", line))?;
+            .with_context(|| format!("Failed to parse chromosome length in line: {}", line))?;
+
+        chrom_sizes.push((chr.to_string(), length));
+    }
+
+    Ok(chrom_sizes)
+}
+
+///
+/// Parse a single line of a BED file into a region. Only the first three columns are used.
+///
+/// # Arguments
+/// - line: the line to parse
+pub fn parse_bed_line(line: &str) -> Result<Region> {
+    let fields: Vec<&str> = line.split('\t').collect();
+
+    // check length of fields
+    if fields.len() < 3 {
+        anyhow::bail!("BED file line does not have at least 3 fields: {}", line);
+    }
+
+    let chr = fields[0];
+    let start = fields[1]
+        .parse::<u32>()
+        .with_context(|| format!("Failed to parse start position in BED file line: {}", line))?;
+    let end = fields[2]
+        .parse::<u32>()
+        .with_context(|| format!("Failed to parse end position in BED file line: {}", line))?;
+
+    Ok(Region {
+        chr: chr.to_string(),
+        start,
+        end,
+    })
+}
 
-        let region = Region {
-            chr: chr.to_string(),
-            start,
-            end,
+///
+/// Merge overlapping or nearby regions, bedtools `merge -d` style, aggregating a value for each merged region.
+///
+/// Regions are sorted by chromosome and start first. Two regions on the same chromosome are merged when the
+/// gap between them is at most `max_distance` (0 merges overlapping and book-ended regions only). Values of
+/// merged regions are combined pairwise with `aggregate`, which covers the usual bedtools operations:
+/// - count: start every value at `1` and aggregate with `a + b`
+/// - sum: aggregate with `a + b`
+/// - max: aggregate with `a.max(b)`
+/// - collect: start every value as `vec![v]` and aggregate by extending `a` with `b`
+///
+/// # Arguments
+/// - regions: the regions to merge, each paired with its value
+/// - max_distance: the largest gap (in bases) that still gets merged
+/// - aggregate: combines the values of two merged regions
+///
+/// # Returns
+/// - the merged regions and their aggregated values, sorted by chromosome and start
+pub fn merge_regions_with<T, F>(
+    mut regions: Vec<(Region, T)>,
+    max_distance: u32,
+    aggregate: F,
+) -> Vec<(Region, T)>
+where
+    F: Fn(T, T) -> T,
+{
+    regions.sort_by(|(a, _), (b, _)| (&a.chr, a.start).cmp(&(&b.chr, b.start)));
+
+    let mut merged: Vec<(Region, T)> = Vec::new();
+    let mut current: Option<(Region, T)> = None;
+
+    for (region, value) in regions {
+        current = match current {
+            Some((mut last, last_value))
+                if last.chr == region.chr
+                    && region.start <= last.end.saturating_add(max_distance) =>
+            {
+                last.end = last.end.max(region.end);
+                Some((last, aggregate(last_value, value)))
+            }
+            Some(last) => {
+                merged.push(last);
+                Some((region, value))
+            }
+            None => Some((region, value)),
         };
+    }
 
-        regions.push(region);
+    if let Some(last) = current {
+        merged.push(last);
     }
 
-    Ok(regions)
+    merged
 }
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+
+use super::*;
+
+pub fn make_consensus_cli() -> Command {
+    Command::new(consts::CONSENSUS_CMD)
+        .about("Build a consensus universe from a directory of bed or fragment files.")
+        .arg(
+            Arg::new("data-dir")
+                .long("data-dir")
+                .short('d')
+                .help("Path to the directory holding the (possibly gzipped) bed or fragment files.")
+                .required(true),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .help("Path to the bed file to write the universe to.")
+                .required(true),
+        )
+        .arg(
+            Arg::new("min-files")
+                .long("min-files")
+                .short('k')
+                .help("The minimum number of files that must support a region.")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("method")
+                .long("method")
+                .short('m')
+                .help("'files' keeps merged regions supported by enough files, 'coverage' keeps bases covered by enough files.")
+                .value_parser(["files", "coverage"])
+                .default_value("files"),
+        )
+}
+
+pub mod handlers {
+
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::*;
+    use crate::common::models::RegionSet;
+
+    pub fn build_consensus(matches: &ArgMatches) -> Result<()> {
+        let data_dir = matches
+            .get_one::<String>("data-dir")
+            .expect("Data directory is required");
+
+        let output = matches
+            .get_one::<String>("output")
+            .expect("Output path is required");
+
+        let min_files = *matches
+            .get_one::<usize>("min-files")
+            .expect("Minimum number of files is required");
+
+        let method = match matches.get_one::<String>("method").map(|m| m.as_str()) {
+            Some("coverage") => ConsensusMethod::Coverage(min_files),
+            _ => ConsensusMethod::Files(min_files),
+        };
+
+        let (universe, stats) = build_consensus_universe(Path::new(data_dir), method)?;
+
+        RegionSet::from(universe.regions)
+            .to_bed(Path::new(output))
+            .with_context(|| "There was an error writing the universe file!")?;
+
+        println!("files\t{}", stats.files);
+        println!("input_regions\t{}", stats.input_regions);
+        println!("universe_regions\t{}", stats.universe_regions);
+        println!("universe_bp\t{}", stats.universe_bp);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! # Consensus universes
+//!
+//! Build a universe directly from a corpus of BED or fragment files, instead of
+//! precomputing one with external tools before tokenizing. Two strategies are supported:
+//!
+//! - [`ConsensusMethod::Files`]: overlapping regions are merged across the whole corpus, and a merged
+//!   region is kept when it is supported by at least `k` different files.
+//! - [`ConsensusMethod::Coverage`]: every base covered by at least `k` files is kept, and runs of kept
+//!   bases become the universe regions. This gives tighter regions than merging.
+//!
+//! Regions within a single file are merged first, so a file counts at most once towards any base.
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::common::models::{Region, Universe};
+use crate::common::utils::{get_dynamic_reader, merge_regions_with, parse_bed_line};
+
+pub mod cli;
+
+pub mod consts {
+    pub const CONSENSUS_CMD: &str = "consensus";
+}
+
+///
+/// How regions from different files are combined into the consensus universe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsensusMethod {
+    /// merge overlapping regions and keep those supported by at least this many files
+    Files(usize),
+    /// keep bases covered by at least this many files
+    Coverage(usize),
+}
+
+///
+/// Summary statistics of a consensus universe build.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ConsensusStats {
+    /// the number of files in the corpus
+    pub files: usize,
+    /// the number of regions read across all files
+    pub input_regions: usize,
+    /// the number of regions in the universe
+    pub universe_regions: usize,
+    /// the number of bases covered by the universe
+    pub universe_bp: u64,
+}
+
+///
+/// Build a consensus universe from every `.bed`, `.bed.gz`, `.tsv`, and `.tsv.gz` (fragment) file
+/// in a directory. Only the first three columns of each file are used, and lines starting with `#`
+/// are skipped. Files are read in parallel.
+///
+/// # Arguments
+/// - data_dir: the directory holding the corpus
+/// - method: how regions from different files are combined
+///
+/// # Returns
+/// - the universe, sorted by chromosome and start, and statistics about the build
+pub fn build_consensus_universe(
+    data_dir: &Path,
+    method: ConsensusMethod,
+) -> Result<(Universe, ConsensusStats)> {
+    let min_files = match method {
+        ConsensusMethod::Files(k) | ConsensusMethod::Coverage(k) => k,
+    };
+    if min_files == 0 {
+        anyhow::bail!("The minimum number of files must be at least 1.");
+    }
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for entry in std::fs::read_dir(data_dir)
+        .with_context(|| format!("Failed to read directory {}", data_dir.display()))?
+    {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if [".bed", ".bed.gz", ".tsv", ".tsv.gz"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let corpus = files
+        .par_iter()
+        .map(|path| read_merged_regions(path))
+        .collect::<Result<Vec<(Vec<Region>, usize)>>>()?;
+
+    let input_regions = corpus.iter().map(|(_, count)| count).sum();
+    let corpus: Vec<Vec<Region>> = corpus.into_iter().map(|(regions, _)| regions).collect();
+
+    let regions = match method {
+        ConsensusMethod::Files(k) => consensus_by_files(corpus, k),
+        ConsensusMethod::Coverage(k) => consensus_by_coverage(corpus, k),
+    };
+
+    let stats = ConsensusStats {
+        files: files.len(),
+        input_regions,
+        universe_regions: regions.len(),
+        universe_bp: regions.iter().map(|r| (r.end - r.start) as u64).sum(),
+    };
+
+    Ok((Universe::from(regions), stats))
+}
+
+///
+/// Read a file and merge its overlapping and book-ended regions. Regions that end before
+/// they start are rejected.
+///
+/// # Returns
+/// - the merged regions, and the number of regions in the file
+fn read_merged_regions(path: &Path) -> Result<(Vec<Region>, usize)> {
+    let reader = get_dynamic_reader(path)?;
+
+    let mut regions: Vec<(Region, ())> = Vec::new();
+    for line in reader.lines() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let region =
+            parse_bed_line(&line).with_context(|| format!("Failed to read {}", path.display()))?;
+        if region.end < region.start {
+            anyhow::bail!(
+                "Region ends before it starts in {}: {}",
+                path.display(),
+                line
+            );
+        }
+        regions.push((region, ()));
+    }
+
+    let count = regions.len();
+    let merged = merge_regions_with(regions, 0, |_, _| ())
+        .into_iter()
+        .map(|(region, _)| region)
+        .collect();
+
+    Ok((merged, count))
+}
+
+fn consensus_by_files(corpus: Vec<Vec<Region>>, min_files: usize) -> Vec<Region> {
+    let regions: Vec<(Region, Vec<usize>)> = corpus
+        .into_iter()
+        .enumerate()
+        .flat_map(|(file, regions)| regions.into_iter().map(move |r| (r, vec![file])))
+        .collect();
+
+    merge_regions_with(regions, 0, |mut a, b| {
+        a.extend(b);
+        a
+    })
+    .into_iter()
+    .filter_map(|(region, mut files)| {
+        files.sort_unstable();
+        files.dedup();
+        (files.len() >= min_files).then_some(region)
+    })
+    .collect()
+}
+
+fn consensus_by_coverage(corpus: Vec<Vec<Region>>, min_files: usize) -> Vec<Region> {
+    // +1 where a file's region starts and -1 where it ends, per chromosome
+    let mut events: BTreeMap<String, Vec<(u32, i64)>> = BTreeMap::new();
+    for region in corpus.into_iter().flatten() {
+        let chr_events = events.entry(region.chr).or_default();
+        chr_events.push((region.start, 1));
+        chr_events.push((region.end, -1));
+    }
+
+    let mut regions: Vec<Region> = Vec::new();
+    for (chr, mut chr_events) in events {
+        chr_events.sort_unstable();
+
+        let mut depth: i64 = 0;
+        let mut run_start: Option<u32> = None;
+        let mut i = 0;
+        while i < chr_events.len() {
+            // apply every event at this position before checking the depth,
+            // so book-ended regions don't split a run
+            let pos = chr_events[i].0;
+            while i < chr_events.len() && chr_events[i].0 == pos {
+                depth += chr_events[i].1;
+                i += 1;
+            }
+
+            let covered = depth >= min_files as i64;
+            match (run_start, covered) {
+                (None, true) => run_start = Some(pos),
+                (Some(start), false) => {
+                    regions.push(Region {
+                        chr: chr.to_owned(),
+                        start,
+                        end: pos,
+                    });
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    regions
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use super::consts::{GTOK_CAT_CMD, GTOK_CMD};
+
+pub fn make_gtok_cli() -> Command {
+    Command::new(GTOK_CMD)
+        .about("Work with .gtok token files.")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new(GTOK_CAT_CMD)
+                .about("Concatenate .gtok files into one indexed file, keeping each document separate.")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Path to the .gtok file to write.")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("inputs")
+                        .help("Paths to the .gtok files to concatenate, in order.")
+                        .action(ArgAction::Append)
+                        .num_args(1..)
+                        .required(true),
+                ),
+        )
+}
+
+pub mod handlers {
+
+    use std::path::Path;
+
+    use super::*;
+    use crate::io::concat_gtok_files;
+
+    pub fn gtok(matches: &ArgMatches) -> Result<()> {
+        match matches.subcommand() {
+            Some((GTOK_CAT_CMD, matches)) => cat_gtok_files(matches),
+            _ => unreachable!("Subcommand not found"),
+        }
+    }
+
+    fn cat_gtok_files(matches: &ArgMatches) -> Result<()> {
+        let output = matches
+            .get_one::<String>("output")
+            .expect("Output path is required");
+
+        let inputs: Vec<&Path> = matches
+            .get_many::<String>("inputs")
+            .expect("Input paths are required")
+            .map(Path::new)
+            .collect();
+
+        let output = Path::new(output);
+        let header = concat_gtok_files(&inputs, output)?;
+        eprintln!(
+            "Wrote {} documents ({} tokens) to {}",
+            header.num_documents(),
+            header.num_tokens(),
+            output.display()
+        );
+
+        Ok(())
+    }
+}
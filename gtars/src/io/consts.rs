@@ -1,3 +1,13 @@
 pub const GTOK_HEADER: &[u8; 4] = b"GTOK";
 pub const GTOK_U16_FLAG: u8 = 0x01;
 pub const GTOK_U32_FLAG: u8 = 0x02;
+/// takes the place of the size flag in files with a version 2 header
+pub const GTOK_V2_FLAG: u8 = 0x10;
+pub const GTOK_FORMAT_VERSION: u8 = 0x02;
+/// set in the size flag of a version 2 header when documents are zstd-compressed
+pub const GTOK_ZSTD_FLAG: u8 = 0x80;
+pub const GTOK_MANIFEST_FILE_NAME: &str = "manifest.tsv";
+
+/// commands for the `gtars` cli
+pub const GTOK_CMD: &str = "gtok";
+pub const GTOK_CAT_CMD: &str = "cat";
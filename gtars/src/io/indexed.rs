@@ -0,0 +1,742 @@
+//! Version 2 of the `.gtok` format: a header with metadata and a document index.
+//!
+//! A version 1 file is the `GTOK` magic bytes, a token size flag, and the tokens. A version 2 file
+//! replaces the size flag with `GTOK_V2_FLAG`, so version 1 readers reject it instead of misreading it,
+//! and lays out the rest of the file as follows (all integers little-endian):
+//!
+//! | field           | size                | notes                                                                                    |
+//! |-----------------|---------------------|------------------------------------------------------------------------------------------|
+//! | version         | 1 byte              | `GTOK_FORMAT_VERSION`                                                                    |
+//! | size flag       | 1 byte              | `GTOK_U16_FLAG` or `GTOK_U32_FLAG`, as in version 1, plus `GTOK_ZSTD_FLAG` if compressed |
+//! | digest length   | 2 bytes             | 0 when the file has no universe digest                                                   |
+//! | universe digest | digest length       | utf-8, see `Universe::digest`                                                            |
+//! | index offset    | 8 bytes             | byte offset of the document count, from the start of the file                            |
+//! | tokens          | up to the index     | in compressed files, one zstd frame per document                                         |
+//! | document count  | 8 bytes             | `n`                                                                                      |
+//! | offsets         | `8 * (n + 1)` bytes | token offset of each document, then the total token count                                |
+//! | frame offsets   | `8 * (n + 1)` bytes | compressed files only: byte offset of each document's data, then its total size          |
+//!
+//! The index comes after the tokens so documents can be appended in place: the old index is copied
+//! further along the file and the index offset pointed at the copy, new tokens overwrite the old
+//! index, a new index is written after them, and the index offset is updated again.
+//!
+//! Compressing each document on its own keeps random access, at some cost in ratio. Writing and reading
+//! compressed files needs the `zstd` feature.
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::reader::GtokReader;
+
+use super::consts::{
+    GTOK_FORMAT_VERSION, GTOK_HEADER, GTOK_U16_FLAG, GTOK_U32_FLAG, GTOK_V2_FLAG, GTOK_ZSTD_FLAG,
+};
+
+/// Everything in a `.gtok` file besides its tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GtokHeader {
+    /// 1 for files without a header, 2 for files written by `write_gtok_documents`
+    pub version: u8,
+    /// the number of bytes per token, 2 or 4
+    pub token_width: u8,
+    /// the digest of the universe the tokens come from, if it was recorded
+    pub universe_digest: Option<String>,
+    /// token offset of each document, followed by the total number of tokens
+    pub offsets: Vec<u64>,
+    /// whether each document is stored as a zstd frame
+    pub compressed: bool,
+    /// for compressed files, the byte offset of each document's frame from `data_start`,
+    /// followed by the total size of the frames; empty otherwise
+    pub frame_offsets: Vec<u64>,
+    /// the byte offset of the first token
+    pub data_start: u64,
+    /// the byte offset of the document index; the end of the file for version 1 files
+    pub index_start: u64,
+}
+
+impl GtokHeader {
+    ///
+    /// The number of documents in the file. A version 1 file is a single document.
+    pub fn num_documents(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    ///
+    /// The number of tokens across all documents.
+    pub fn num_tokens(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    ///
+    /// The byte range of a document's tokens (or its zstd frame) in the file.
+    pub fn document_bytes(&self, index: usize) -> Option<(u64, u64)> {
+        if self.compressed {
+            let start = *self.frame_offsets.get(index)?;
+            let end = *self.frame_offsets.get(index + 1)?;
+            return Some((self.data_start + start, self.data_start + end));
+        }
+
+        let start = *self.offsets.get(index)?;
+        let end = *self.offsets.get(index + 1)?;
+        let width = self.token_width as u64;
+
+        Some((
+            self.data_start + start * width,
+            self.data_start + end * width,
+        ))
+    }
+
+    ///
+    /// The byte offset just past the last document.
+    pub fn data_end(&self) -> u64 {
+        match self.compressed {
+            true => self.data_start + self.frame_offsets.last().copied().unwrap_or_default(),
+            false => self.data_start + self.num_tokens() * self.token_width as u64,
+        }
+    }
+
+    ///
+    /// Make sure the tokens come from the universe with `digest`. Files that don't record a
+    /// digest, like every version 1 file, can't be checked and are accepted.
+    pub fn check_universe_digest(&self, digest: &str) -> Result<()> {
+        match &self.universe_digest {
+            Some(file_digest) if file_digest != digest => anyhow::bail!(
+                "The tokens come from universe {}, not this universe ({})",
+                file_digest,
+                digest
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn checked_document_bytes(&self, index: usize) -> Result<(u64, u64)> {
+        self.document_bytes(index).with_context(|| {
+            format!(
+                "Document {} is out of range, the file has {} documents",
+                index,
+                self.num_documents()
+            )
+        })
+    }
+}
+
+fn token_width(flag: u8) -> Result<u8> {
+    match flag {
+        GTOK_U16_FLAG => Ok(2),
+        GTOK_U32_FLAG => Ok(4),
+        _ => anyhow::bail!("Invalid data format flag found in gtok file"),
+    }
+}
+
+fn decode_tokens(bytes: &[u8], token_width: u8) -> Vec<u32> {
+    match token_width {
+        2 => bytes
+            .chunks_exact(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]) as u32)
+            .collect(),
+        _ => bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+    }
+}
+
+///
+/// Turn the bytes of one document, as stored in the file, back into token ids.
+pub(crate) fn decode_document(header: &GtokHeader, bytes: &[u8]) -> Result<Vec<u32>> {
+    if !header.compressed {
+        return Ok(decode_tokens(bytes, header.token_width));
+    }
+
+    #[cfg(feature = "zstd")]
+    {
+        let bytes =
+            zstd::decode_all(bytes).with_context(|| "Failed to decompress gtok document")?;
+        Ok(decode_tokens(&bytes, header.token_width))
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    anyhow::bail!(
+        "This gtok file is zstd-compressed, but gtars was built without the `zstd` feature."
+    )
+}
+
+fn encode_tokens(tokens: &[u32], flag: u8) -> Vec<u8> {
+    match flag {
+        GTOK_U16_FLAG => tokens
+            .iter()
+            .flat_map(|&token| (token as u16).to_le_bytes())
+            .collect(),
+        _ => tokens
+            .iter()
+            .flat_map(|token| token.to_le_bytes())
+            .collect(),
+    }
+}
+
+///
+/// Encode a document's tokens as they're stored in the file, as one zstd frame if `level`
+/// is given.
+pub(crate) fn encode_document(tokens: &[u32], flag: u8, level: Option<i32>) -> Result<Vec<u8>> {
+    let bytes = encode_tokens(tokens, flag);
+    let Some(level) = level else {
+        return Ok(bytes);
+    };
+
+    #[cfg(feature = "zstd")]
+    {
+        zstd::encode_all(&bytes[..], level).with_context(|| "Failed to compress gtok document")
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    {
+        let _ = level;
+        anyhow::bail!(
+            "Writing zstd-compressed gtok files needs gtars to be built with the `zstd` feature."
+        )
+    }
+}
+
+fn size_flag(docs: &[Vec<u32>]) -> u8 {
+    match docs.iter().flatten().all(|&x| x <= u16::MAX as u32) {
+        true => GTOK_U16_FLAG,
+        false => GTOK_U32_FLAG,
+    }
+}
+
+///
+/// Streams documents into a version 2 `.gtok` file. The index is kept in memory and written
+/// after the tokens by `finish`, so documents never have to be held in memory together.
+pub(crate) struct GtokDocumentWriter {
+    writer: BufWriter<File>,
+    flag: u8,
+    /// the zstd level documents are compressed with, if the file is compressed
+    level: Option<i32>,
+    data_start: u64,
+    offsets: Vec<u64>,
+    frame_offsets: Vec<u64>,
+}
+
+impl GtokDocumentWriter {
+    ///
+    /// Create a new file and write its header.
+    ///
+    /// # Arguments
+    /// - path: the file to create
+    /// - flag: `GTOK_U16_FLAG` or `GTOK_U32_FLAG`
+    /// - universe_digest: the digest of the universe the tokens come from
+    /// - level: the zstd level to compress documents with, or `None` to store them as is
+    pub(crate) fn create(
+        path: &Path,
+        flag: u8,
+        universe_digest: Option<&str>,
+        level: Option<i32>,
+    ) -> Result<Self> {
+        let digest = universe_digest.unwrap_or_default();
+        if digest.len() > u16::MAX as usize {
+            anyhow::bail!("Universe digest is too long for a gtok header.");
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path).with_context(|| "Failed to create gtok file!")?;
+        let mut writer = BufWriter::new(file);
+
+        writer
+            .write_all(GTOK_HEADER)
+            .with_context(|| "Failed to write GTOK header to file!")?;
+        let size_flag = match level {
+            Some(_) => flag | GTOK_ZSTD_FLAG,
+            None => flag,
+        };
+        writer.write_all(&[GTOK_V2_FLAG, GTOK_FORMAT_VERSION, size_flag])?;
+        writer.write_all(&(digest.len() as u16).to_le_bytes())?;
+        writer.write_all(digest.as_bytes())?;
+        // the index offset is filled in by `finish`
+        writer.write_all(&0u64.to_le_bytes())?;
+
+        Ok(GtokDocumentWriter {
+            data_start: writer.stream_position()?,
+            writer,
+            flag,
+            level,
+            offsets: vec![0],
+            frame_offsets: vec![0],
+        })
+    }
+
+    ///
+    /// Reopen an existing version 2 file to add documents after the ones it has. The new
+    /// documents overwrite its index, so call `keep_index` before writing any of them.
+    ///
+    /// # Arguments
+    /// - path: the file to append to
+    /// - header: the file's header
+    /// - level: the zstd level to compress new documents with, if the file is compressed
+    pub(crate) fn resume(path: &Path, header: &GtokHeader, level: i32) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| "Failed to open gtok file for appending")?;
+        let mut writer = BufWriter::new(file);
+        writer.seek(SeekFrom::Start(header.data_end()))?;
+
+        Ok(GtokDocumentWriter {
+            writer,
+            flag: match header.token_width {
+                2 => GTOK_U16_FLAG,
+                _ => GTOK_U32_FLAG,
+            },
+            level: header.compressed.then_some(level),
+            data_start: header.data_start,
+            offsets: header.offsets.to_owned(),
+            frame_offsets: match header.compressed {
+                true => header.frame_offsets.to_owned(),
+                false => vec![0],
+            },
+        })
+    }
+
+    ///
+    /// Copy the file's current index to `position` and point the header at the copy, so the
+    /// file can still be read while new documents overwrite the original. `position` has to
+    /// be past the end of the index `finish` will write; `finish` drops the copy.
+    ///
+    /// # Arguments
+    /// - header: the file's header, as passed to `resume`
+    /// - position: where to put the copy
+    pub(crate) fn keep_index(&mut self, header: &GtokHeader, position: u64) -> Result<()> {
+        let resume_at = self.writer.stream_position()?;
+        let file = self.writer.get_mut();
+
+        let mut index = vec![0; index_size(header.num_documents(), header.compressed) as usize];
+        file.seek(SeekFrom::Start(header.index_start))?;
+        file.read_exact(&mut index)?;
+
+        let position = position.max(file.seek(SeekFrom::End(0))?);
+        file.seek(SeekFrom::Start(position))?;
+        file.write_all(&index)
+            .with_context(|| "Failed to write bytes to file!")?;
+
+        // only once the copy is written does the header point at it
+        file.seek(SeekFrom::Start(self.data_start - 8))?;
+        file.write_all(&position.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(resume_at))?;
+        Ok(())
+    }
+
+    pub(crate) fn num_tokens(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    ///
+    /// Write one document.
+    pub(crate) fn write_document(&mut self, tokens: &[u32]) -> Result<()> {
+        let bytes = encode_document(tokens, self.flag, self.level)?;
+        self.write_encoded(&bytes, tokens.len() as u64)
+    }
+
+    ///
+    /// Write one document that was already encoded with `encode_document`, with this
+    /// writer's flag and level.
+    pub(crate) fn write_encoded(&mut self, bytes: &[u8], num_tokens: u64) -> Result<()> {
+        self.writer
+            .write_all(bytes)
+            .with_context(|| "Failed to write bytes to file!")?;
+
+        self.offsets.push(self.num_tokens() + num_tokens);
+        let frame_end = *self.frame_offsets.last().unwrap() + bytes.len() as u64;
+        self.frame_offsets.push(frame_end);
+
+        Ok(())
+    }
+
+    ///
+    /// Write the index after the last document and point the header at it.
+    pub(crate) fn finish(mut self) -> Result<()> {
+        let index_start = self.writer.stream_position()?;
+
+        self.writer
+            .write_all(&(self.offsets.len() as u64 - 1).to_le_bytes())?;
+        let frame_offsets: &[u64] = match self.level {
+            Some(_) => &self.frame_offsets[..],
+            None => &[],
+        };
+        for offset in self.offsets.iter().chain(frame_offsets) {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        let end = self.writer.stream_position()?;
+
+        // the index is written out before the header points at it
+        let mut file = self
+            .writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .with_context(|| "Failed to write bytes to file!")?;
+        // the index offset sits just before the first token
+        file.seek(SeekFrom::Start(self.data_start - 8))?;
+        file.write_all(&index_start.to_le_bytes())?;
+        // drop anything past the index, e.g. left by an earlier append that failed
+        file.set_len(end)?;
+
+        Ok(())
+    }
+}
+
+///
+/// Write documents to a version 2 `.gtok` file, with an index so each one can be read on its own.
+///
+/// # Arguments
+/// - filename: the file to save the documents to
+/// - docs: the token ids of each document
+/// - universe_digest: the digest of the universe the tokens come from, so readers can check they
+///   decode the file with the right universe
+pub fn write_gtok_documents(
+    filename: &str,
+    docs: &[Vec<u32>],
+    universe_digest: Option<&str>,
+) -> Result<()> {
+    let mut writer =
+        GtokDocumentWriter::create(Path::new(filename), size_flag(docs), universe_digest, None)?;
+    for doc in docs {
+        writer.write_document(doc)?;
+    }
+
+    writer.finish()
+}
+
+///
+/// Write documents to a version 2 `.gtok` file like `write_gtok_documents`, compressing each
+/// document into its own zstd frame so they can still be read one at a time. Documents are
+/// compressed in parallel.
+///
+/// # Arguments
+/// - filename: the file to save the documents to
+/// - docs: the token ids of each document
+/// - universe_digest: the digest of the universe the tokens come from
+/// - level: the zstd compression level, 0 for zstd's default
+#[cfg(feature = "zstd")]
+pub fn write_compressed_gtok_documents(
+    filename: &str,
+    docs: &[Vec<u32>],
+    universe_digest: Option<&str>,
+    level: i32,
+) -> Result<()> {
+    use rayon::prelude::*;
+
+    let flag = size_flag(docs);
+    let frames = docs
+        .par_iter()
+        .map(|doc| encode_document(doc, flag, Some(level)))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+
+    let mut writer =
+        GtokDocumentWriter::create(Path::new(filename), flag, universe_digest, Some(level))?;
+    for (frame, doc) in frames.iter().zip(docs) {
+        writer.write_encoded(frame, doc.len() as u64)?;
+    }
+
+    writer.finish()
+}
+
+///
+/// Concatenate `.gtok` files of either version into one version 2 file, keeping every
+/// document separate in the new index. A version 1 input counts as a single document.
+/// Documents are streamed one at a time, so the inputs never have to fit in memory.
+/// Compressed inputs are decompressed; the new file is always uncompressed.
+///
+/// Inputs must all have the same universe digest, or all have none: a file without a
+/// digest (including every version 1 file) can't be checked against the others.
+///
+/// # Arguments
+/// - inputs: the files to concatenate, in order
+/// - output: the file to write, which must not be one of the inputs
+///
+/// # Returns
+/// - the header of the new file
+pub fn concat_gtok_files(inputs: &[&Path], output: &Path) -> Result<GtokHeader> {
+    // creating the output truncates it, so it can't be an input under any name; a missing
+    // output can't be an input either, since every input has to exist
+    if let Ok(output) = output.canonicalize() {
+        for path in inputs {
+            let input = path
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve {}", path.display()))?;
+            if input == output {
+                anyhow::bail!(
+                    "The output file can't also be an input file: {}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    let readers = inputs
+        .iter()
+        .map(|path| GtokReader::open(path))
+        .collect::<Result<Vec<GtokReader>>>()?;
+
+    // inputs written against different universes can't share a file
+    let digest = match readers.first() {
+        Some(reader) => reader.header().universe_digest.to_owned(),
+        None => None,
+    };
+    for (reader, path) in readers.iter().zip(inputs) {
+        match (&digest, &reader.header().universe_digest) {
+            (Some(digest), Some(other)) if digest != other => anyhow::bail!(
+                "{} was written with a different universe than the files before it",
+                path.display()
+            ),
+            (Some(_), None) | (None, Some(_)) => anyhow::bail!(
+                "{} can't be concatenated: some inputs record a universe digest and some don't",
+                path.display()
+            ),
+            _ => {}
+        }
+    }
+
+    let flag = if readers.iter().all(|r| r.header().token_width == 2) {
+        GTOK_U16_FLAG
+    } else {
+        GTOK_U32_FLAG
+    };
+
+    let mut writer = GtokDocumentWriter::create(output, flag, digest.as_deref(), None)?;
+    for reader in readers {
+        for doc in reader.documents() {
+            writer.write_document(&doc?)?;
+        }
+    }
+    writer.finish()?;
+
+    read_gtok_header(output.to_str().unwrap_or_default())
+}
+
+///
+/// Append documents to a `.gtok` file, updating its index. A version 2 file is appended to in
+/// place: only the new tokens and the index are written, and compressed files stay compressed.
+/// The file has to be rewritten through a temporary file next to it when it's a version 1 file,
+/// which becomes a version 2 file whose first document is its old contents, or when the new
+/// documents need wider tokens than the file has.
+///
+/// An in-place append first copies the old index past where the new one will end and points the
+/// header at the copy, so the file stays readable, with its old documents, if the append fails
+/// part way through.
+///
+/// # Arguments
+/// - filename: the file to append to
+/// - docs: the token ids of each new document
+/// - level: the zstd level to compress new documents with if the file is compressed, 0 for
+///   zstd's default
+pub fn append_gtok_documents(filename: &str, docs: &[Vec<u32>], level: i32) -> Result<()> {
+    let path = Path::new(filename);
+    let header = read_gtok_header(filename)?;
+
+    let widen = header.token_width == 2 && size_flag(docs) == GTOK_U32_FLAG;
+    if header.version == 1 || widen {
+        return rewrite_gtok_file(path, &header, docs, level);
+    }
+
+    let mut writer = GtokDocumentWriter::resume(path, &header, level)?;
+    let encoded = docs
+        .iter()
+        .map(|doc| encode_document(doc, writer.flag, writer.level))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+
+    // the new documents and index end here, so a copy of the old index after this survives them
+    let new_bytes: u64 = encoded.iter().map(|bytes| bytes.len() as u64).sum();
+    let index_len = index_size(header.num_documents() + docs.len(), header.compressed);
+    writer.keep_index(&header, header.data_end() + new_bytes + index_len)?;
+
+    for (bytes, doc) in encoded.iter().zip(docs) {
+        writer.write_encoded(bytes, doc.len() as u64)?;
+    }
+
+    writer.finish()
+}
+
+///
+/// The size in bytes of the index of a version 2 file with `num_documents` documents.
+fn index_size(num_documents: usize, compressed: bool) -> u64 {
+    let columns = if compressed { 2 } else { 1 };
+    8 + 8 * (num_documents as u64 + 1) * columns
+}
+
+///
+/// Rewrite a `.gtok` file as a version 2 file with the documents it has followed by `docs`,
+/// widening its tokens if needed and keeping its digest and compression.
+fn rewrite_gtok_file(
+    path: &Path,
+    header: &GtokHeader,
+    docs: &[Vec<u32>],
+    level: i32,
+) -> Result<()> {
+    let flag = match header.token_width {
+        2 => size_flag(docs),
+        _ => GTOK_U32_FLAG,
+    };
+    let level = header.compressed.then_some(level);
+
+    let combined = path.with_extension("gtok.tmp");
+    let result = (|| -> Result<()> {
+        let mut writer =
+            GtokDocumentWriter::create(&combined, flag, header.universe_digest.as_deref(), level)?;
+        for doc in GtokReader::open(path)?.documents() {
+            writer.write_document(&doc?)?;
+        }
+        for doc in docs {
+            writer.write_document(doc)?;
+        }
+        writer.finish()?;
+
+        std::fs::rename(&combined, path).with_context(|| "Failed to replace gtok file!")
+    })();
+
+    // clean up whatever is left over, the result is what matters
+    let _ = std::fs::remove_file(&combined);
+
+    result
+}
+
+///
+/// Read the header of a `.gtok` file of either version, leaving the reader at the first token.
+pub(crate) fn read_header<R: Read + Seek>(reader: &mut R) -> Result<GtokHeader> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != GTOK_HEADER {
+        anyhow::bail!("File doesn't appear to be a valid .gtok file.")
+    }
+
+    let mut flag = [0; 1];
+    reader.read_exact(&mut flag)?;
+
+    if flag[0] != GTOK_V2_FLAG {
+        // version 1: the rest of the file is one run of tokens
+        let token_width = token_width(flag[0])?;
+        let data_start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(data_start))?;
+
+        return Ok(GtokHeader {
+            version: 1,
+            token_width,
+            universe_digest: None,
+            offsets: vec![0, (end - data_start) / token_width as u64],
+            compressed: false,
+            frame_offsets: Vec::new(),
+            data_start,
+            index_start: end,
+        });
+    }
+
+    let mut fixed = [0; 4];
+    reader.read_exact(&mut fixed)?;
+    let [version, flag, len_lo, len_hi] = fixed;
+    if version != GTOK_FORMAT_VERSION {
+        anyhow::bail!("Unsupported gtok format version: {}", version)
+    }
+    let compressed = flag & GTOK_ZSTD_FLAG != 0;
+    let token_width = token_width(flag & !GTOK_ZSTD_FLAG)?;
+
+    let mut digest = vec![0; u16::from_le_bytes([len_lo, len_hi]) as usize];
+    reader.read_exact(&mut digest)?;
+    let digest =
+        String::from_utf8(digest).with_context(|| "Invalid universe digest in gtok file")?;
+
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+    let index_start = u64::from_le_bytes(buffer);
+    let data_start = reader.stream_position()?;
+    let file_end = reader.seek(SeekFrom::End(0))?;
+
+    if index_start < data_start || index_start > file_end.saturating_sub(8) {
+        anyhow::bail!("Invalid gtok file: its index offset is outside the file")
+    }
+    reader.seek(SeekFrom::Start(index_start))?;
+    reader.read_exact(&mut buffer)?;
+    let num_documents = u64::from_le_bytes(buffer);
+
+    // the document count comes straight from the file, so make sure the index it
+    // implies fits in what's left of the file before allocating anything for it
+    let index_bytes = num_documents
+        .checked_add(1)
+        .and_then(|n| n.checked_mul(if compressed { 16 } else { 8 }));
+    match index_bytes {
+        Some(index_bytes) if index_bytes <= file_end - index_start - 8 => {}
+        _ => anyhow::bail!(
+            "Invalid gtok file: the index of {} documents doesn't fit in the file",
+            num_documents
+        ),
+    }
+
+    let mut read_offsets = |name: &str| -> Result<Vec<u64>> {
+        let mut offsets = Vec::with_capacity(num_documents as usize + 1);
+        for _ in 0..=num_documents {
+            reader.read_exact(&mut buffer)?;
+            offsets.push(u64::from_le_bytes(buffer));
+        }
+
+        if offsets[0] != 0 || offsets.windows(2).any(|window| window[1] < window[0]) {
+            anyhow::bail!(
+                "Invalid gtok file: {} must start at 0 and never decrease",
+                name
+            );
+        }
+        Ok(offsets)
+    };
+    let offsets = read_offsets("token offsets")?;
+    let frame_offsets = match compressed {
+        true => read_offsets("frame offsets")?,
+        false => Vec::new(),
+    };
+
+    // every document's bytes have to come before the index; with this and the offsets never
+    // decreasing, no byte range computed from the index can overflow or run backwards
+    let data_size = match compressed {
+        true => frame_offsets.last().copied(),
+        false => offsets.last().unwrap().checked_mul(token_width as u64),
+    };
+    match data_size {
+        Some(size) if size <= index_start - data_start => {}
+        _ => anyhow::bail!("Invalid gtok file: its documents run into its index"),
+    }
+
+    reader.seek(SeekFrom::Start(data_start))?;
+
+    Ok(GtokHeader {
+        version,
+        token_width,
+        universe_digest: (!digest.is_empty()).then_some(digest),
+        offsets,
+        compressed,
+        frame_offsets,
+        data_start,
+        index_start,
+    })
+}
+
+///
+/// Read the header and document index of a `.gtok` file. Version 1 files are reported as
+/// a single document without a universe digest.
+///
+/// # Arguments
+/// - filename: the file to read
+pub fn read_gtok_header(filename: &str) -> Result<GtokHeader> {
+    let file = File::open(filename).with_context(|| "Failed to open gtok file!")?;
+    read_header(&mut BufReader::new(file))
+}
+
+///
+/// Read one document from a `.gtok` file, seeking straight to it with the document index.
+///
+/// # Arguments
+/// - filename: the file to read
+/// - index: the position of the document in the file
+pub fn read_gtok_document_at(filename: &str, index: usize) -> Result<Vec<u32>> {
+    GtokReader::open(Path::new(filename))?.document(index)
+}
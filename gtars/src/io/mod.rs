@@ -4,9 +4,22 @@ use std::io::{BufReader, BufWriter, Read, Write};
 
 use anyhow::{Context, Result};
 
+pub mod cli;
 pub mod consts;
+pub mod indexed;
+pub mod reader;
+pub mod sharded;
 
-use consts::{GTOK_HEADER, GTOK_U16_FLAG, GTOK_U32_FLAG};
+use consts::{GTOK_HEADER, GTOK_U16_FLAG, GTOK_U32_FLAG, GTOK_V2_FLAG};
+
+#[cfg(feature = "zstd")]
+pub use indexed::write_compressed_gtok_documents;
+pub use indexed::{
+    append_gtok_documents, concat_gtok_files, read_gtok_document_at, read_gtok_header,
+    write_gtok_documents, GtokHeader,
+};
+pub use reader::{GtokDocuments, GtokReader, MmapGtokReader};
+pub use sharded::{read_gtok_document, read_gtok_manifest, ManifestEntry, ShardedGtokWriter};
 
 ///
 /// Writes a vector of tokens to a file in the `.gtok` format.
@@ -95,6 +108,12 @@ pub fn read_tokens_from_gtok(filename: &str) -> Result<Vec<u32>> {
                 tokens.push(u32::from_le_bytes(buffer));
             }
         }
+        [GTOK_V2_FLAG] => {
+            // all documents of an indexed file, back to back
+            for document in GtokReader::open(std::path::Path::new(filename))?.documents() {
+                tokens.extend(document?);
+            }
+        }
         _ => {
             anyhow::bail!("Invalid data format flag found in gtok file")
         }
@@ -175,6 +194,9 @@ pub fn append_tokens_to_gtok_file(filename: &str, tokens: &[u32]) -> Result<()>
                     .with_context(|| "Failed to write bytes to file!")?;
             }
         }
+        [GTOK_V2_FLAG] => {
+            anyhow::bail!("Can't append raw tokens to an indexed (version 2) gtok file")
+        }
         _ => {
             anyhow::bail!("Invalid data format flag found in gtok file")
         }
@@ -0,0 +1,158 @@
+//! Lazy and random-access readers for `.gtok` files of either version.
+//!
+//! `GtokReader` reads through a buffered file handle, so documents are only read when asked for.
+//! `MmapGtokReader` maps the file into memory instead, which makes sampling documents at random
+//! cheap and lets the operating system decide what stays in memory.
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+
+use super::indexed::{decode_document, read_header, GtokHeader};
+
+///
+/// A `.gtok` file opened for reading. Version 1 files hold a single document.
+pub struct GtokReader {
+    reader: BufReader<File>,
+    header: GtokHeader,
+}
+
+impl GtokReader {
+    ///
+    /// Open a `.gtok` file and read its header.
+    ///
+    /// # Arguments
+    /// - path: the file to read
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open gtok file {}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let header = read_header(&mut reader)?;
+
+        Ok(GtokReader { reader, header })
+    }
+
+    pub fn header(&self) -> &GtokHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.num_documents()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// Read a single document, seeking straight to it.
+    ///
+    /// # Arguments
+    /// - index: the position of the document in the file
+    pub fn document(&mut self, index: usize) -> Result<Vec<u32>> {
+        let (start, end) = self.header.checked_document_bytes(index)?;
+
+        self.reader.seek(SeekFrom::Start(start))?;
+        let mut bytes = vec![0; (end - start) as usize];
+        self.reader
+            .read_exact(&mut bytes)
+            .with_context(|| "Unexpected end of gtok file")?;
+
+        decode_document(&self.header, &bytes)
+    }
+
+    ///
+    /// Iterate over the documents in file order, reading each one only when it's reached.
+    pub fn documents(self) -> GtokDocuments {
+        GtokDocuments {
+            reader: self,
+            next: 0,
+        }
+    }
+}
+
+///
+/// An iterator over the documents of a `.gtok` file, see `GtokReader::documents`.
+pub struct GtokDocuments {
+    reader: GtokReader,
+    next: usize,
+}
+
+impl Iterator for GtokDocuments {
+    type Item = Result<Vec<u32>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.reader.len() {
+            return None;
+        }
+
+        let document = self.reader.document(self.next);
+        self.next += 1;
+
+        Some(document)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.reader.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+///
+/// A memory-mapped `.gtok` file, for random access to its documents from many places at once.
+/// The file must not be modified while it's mapped.
+pub struct MmapGtokReader {
+    mmap: Mmap,
+    header: GtokHeader,
+}
+
+impl MmapGtokReader {
+    ///
+    /// Map a `.gtok` file into memory and read its header.
+    ///
+    /// # Arguments
+    /// - path: the file to map
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open gtok file {}", path.display()))?;
+        // safety: gtok files are written once and not modified afterwards
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("Failed to memory-map {}", path.display()))?;
+        let header = read_header(&mut std::io::Cursor::new(&mmap[..]))?;
+
+        if header.data_end() > mmap.len() as u64 {
+            anyhow::bail!("{} is shorter than its document index says", path.display());
+        }
+
+        Ok(MmapGtokReader { mmap, header })
+    }
+
+    pub fn header(&self) -> &GtokHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.num_documents()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///
+    /// Read a single document.
+    ///
+    /// # Arguments
+    /// - index: the position of the document in the file
+    pub fn document(&self, index: usize) -> Result<Vec<u32>> {
+        let (start, end) = self.header.checked_document_bytes(index)?;
+        let bytes = self
+            .mmap
+            .get(start as usize..end as usize)
+            .with_context(|| "Unexpected end of gtok file")?;
+
+        decode_document(&self.header, bytes)
+    }
+}
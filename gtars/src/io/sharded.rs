@@ -0,0 +1,284 @@
+//! Writing a corpus of tokenized documents as sharded `.gtok` files.
+//!
+//! Each shard is a version 2 `.gtok` file (see `indexed`) holding whole documents, so its own
+//! document index says where each one starts. Shards always use 32-bit tokens, since documents
+//! are streamed in before the largest token of a shard is known. A `manifest.tsv` file next to
+//! the shards records which shard every document lives in and its position there, which lets
+//! training code read documents in any (e.g. shuffled) order without scanning the shards.
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+
+use super::consts::{GTOK_MANIFEST_FILE_NAME, GTOK_U32_FLAG};
+use super::indexed::GtokDocumentWriter;
+use super::reader::GtokReader;
+
+/// Where one document lives in a sharded corpus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub doc_id: u64,
+    /// the shard's file name, relative to the corpus directory
+    pub shard: String,
+    /// the document's position in the shard's document index
+    pub index: u64,
+    /// the number of tokens in the document
+    pub length: u64,
+}
+
+///
+/// Writes tokenized documents into `.gtok` shards of bounded size, and a manifest of
+/// where each document ended up. Documents are never split across shards, so a single
+/// document longer than the limit gets a shard of its own.
+pub struct ShardedGtokWriter {
+    dir: PathBuf,
+    max_tokens_per_shard: u64,
+    writer: Option<GtokDocumentWriter>,
+    shard: usize,
+    shard_tokens: u64,
+    shard_documents: u64,
+    manifest: Vec<ManifestEntry>,
+}
+
+fn shard_name(shard: usize) -> String {
+    format!("shard_{:05}.gtok", shard)
+}
+
+impl ShardedGtokWriter {
+    ///
+    /// Create a writer that puts its shards and manifest in `dir`, creating it if needed.
+    ///
+    /// # Arguments
+    /// - dir: the directory to write the corpus to
+    /// - max_tokens_per_shard: the number of tokens after which a new shard is started
+    pub fn new(dir: &Path, max_tokens_per_shard: u64) -> Result<Self> {
+        if max_tokens_per_shard == 0 {
+            anyhow::bail!("The maximum number of tokens per shard must be positive.");
+        }
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+
+        Ok(ShardedGtokWriter {
+            dir: dir.to_path_buf(),
+            max_tokens_per_shard,
+            writer: None,
+            shard: 0,
+            shard_tokens: 0,
+            shard_documents: 0,
+            manifest: Vec::new(),
+        })
+    }
+
+    ///
+    /// Write one document.
+    ///
+    /// # Returns
+    /// - the document's id, which is its position in the order documents were written
+    pub fn write(&mut self, tokens: &[u32]) -> Result<u64> {
+        if self.starts_new_shard(tokens.len() as u64) {
+            self.finish_shard()?;
+        }
+        if self.writer.is_none() {
+            self.writer = Some(self.create_shard(self.shard)?);
+        }
+
+        self.writer.as_mut().unwrap().write_document(tokens)?;
+        Ok(self.record(tokens.len() as u64))
+    }
+
+    ///
+    /// Write many documents. Documents that fit in the open shard are added to it, and
+    /// the rest are split into new shards up front, which are then written in parallel,
+    /// each by its own writer. Documents get consecutive ids in the order they're given.
+    ///
+    /// # Returns
+    /// - the id of each document
+    pub fn write_batch(&mut self, docs: &[Vec<u32>]) -> Result<Vec<u64>> {
+        let mut ids = Vec::with_capacity(docs.len());
+
+        let mut remaining = docs;
+        while let Some((tokens, rest)) = remaining.split_first() {
+            if self.writer.is_none() || self.starts_new_shard(tokens.len() as u64) {
+                break;
+            }
+            ids.push(self.write(tokens)?);
+            remaining = rest;
+        }
+        if remaining.is_empty() {
+            return Ok(ids);
+        }
+        self.finish_shard()?;
+
+        // split the rest into shards the same way `write` would
+        let mut groups: Vec<&[Vec<u32>]> = Vec::new();
+        let (mut group_start, mut group_tokens) = (0, 0);
+        for (i, tokens) in remaining.iter().enumerate() {
+            let length = tokens.len() as u64;
+            if group_tokens > 0 && group_tokens + length > self.max_tokens_per_shard {
+                groups.push(&remaining[group_start..i]);
+                (group_start, group_tokens) = (i, 0);
+            }
+            group_tokens += length;
+        }
+        groups.push(&remaining[group_start..]);
+
+        let first_shard = self.shard;
+        let mut writers = groups
+            .par_iter()
+            .enumerate()
+            .map(|(i, group)| {
+                let mut writer = self.create_shard(first_shard + i)?;
+                for tokens in group.iter() {
+                    writer.write_document(tokens)?;
+                }
+                Ok(writer)
+            })
+            .collect::<Result<Vec<GtokDocumentWriter>>>()?;
+
+        // the last shard may still have room, so it stays open for later documents
+        let last = writers.pop();
+        for writer in writers {
+            writer.finish()?;
+        }
+
+        for (i, group) in groups.into_iter().enumerate() {
+            self.shard = first_shard + i;
+            (self.shard_tokens, self.shard_documents) = (0, 0);
+            for tokens in group.iter() {
+                ids.push(self.record(tokens.len() as u64));
+            }
+        }
+        self.writer = last;
+
+        Ok(ids)
+    }
+
+    fn starts_new_shard(&self, length: u64) -> bool {
+        self.shard_tokens > 0 && self.shard_tokens + length > self.max_tokens_per_shard
+    }
+
+    fn create_shard(&self, shard: usize) -> Result<GtokDocumentWriter> {
+        let path = self.dir.join(shard_name(shard));
+        GtokDocumentWriter::create(&path, GTOK_U32_FLAG, None, None)
+            .with_context(|| format!("Failed to create shard {}", path.display()))
+    }
+
+    ///
+    /// Add a document written to the current shard to the manifest.
+    fn record(&mut self, length: u64) -> u64 {
+        let doc_id = self.manifest.len() as u64;
+        self.manifest.push(ManifestEntry {
+            doc_id,
+            shard: shard_name(self.shard),
+            index: self.shard_documents,
+            length,
+        });
+        self.shard_tokens += length;
+        self.shard_documents += 1;
+
+        doc_id
+    }
+
+    fn finish_shard(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+            self.shard += 1;
+        }
+        self.shard_tokens = 0;
+        self.shard_documents = 0;
+
+        Ok(())
+    }
+
+    ///
+    /// Finish the last shard and write the manifest.
+    ///
+    /// # Returns
+    /// - the manifest entries, in document id order
+    pub fn finish(mut self) -> Result<Vec<ManifestEntry>> {
+        self.finish_shard()?;
+
+        let path = self.dir.join(GTOK_MANIFEST_FILE_NAME);
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create manifest {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "doc_id\tshard\tindex\tlength")?;
+        for entry in self.manifest.iter() {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}",
+                entry.doc_id, entry.shard, entry.index, entry.length
+            )?;
+        }
+        writer.flush()?;
+
+        Ok(self.manifest)
+    }
+}
+
+///
+/// Read the manifest of a sharded corpus.
+///
+/// # Arguments
+/// - dir: the corpus directory
+pub fn read_gtok_manifest(dir: &Path) -> Result<Vec<ManifestEntry>> {
+    let path = dir.join(GTOK_MANIFEST_FILE_NAME);
+    let file =
+        File::open(&path).with_context(|| format!("Failed to open manifest {}", path.display()))?;
+
+    let mut entries = Vec::new();
+    // skip the column names
+    for line in BufReader::new(file).lines().skip(1) {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 {
+            anyhow::bail!("Manifest line does not have 4 fields: {}", line);
+        }
+
+        let parse = |field: &str| -> Result<u64> {
+            field
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse manifest line: {}", line))
+        };
+        entries.push(ManifestEntry {
+            doc_id: parse(fields[0])?,
+            shard: fields[1].to_string(),
+            index: parse(fields[2])?,
+            length: parse(fields[3])?,
+        });
+    }
+
+    Ok(entries)
+}
+
+///
+/// Read a single document from a sharded corpus without reading the rest of its shard.
+/// The shard's document index is checked before anything is read, so a manifest that
+/// doesn't match its shards gives an error instead of the wrong tokens.
+///
+/// # Arguments
+/// - dir: the corpus directory
+/// - entry: the document's manifest entry
+pub fn read_gtok_document(dir: &Path, entry: &ManifestEntry) -> Result<Vec<u32>> {
+    let path = dir.join(&entry.shard);
+    let index = usize::try_from(entry.index)
+        .with_context(|| format!("Document {} has an invalid index", entry.doc_id))?;
+
+    let tokens = GtokReader::open(&path)?
+        .document(index)
+        .with_context(|| format!("Failed to read document {}", entry.doc_id))?;
+    if tokens.len() as u64 != entry.length {
+        anyhow::bail!(
+            "Document {} has {} tokens in {}, but the manifest says {}",
+            entry.doc_id,
+            tokens.len(),
+            path.display(),
+            entry.length
+        );
+    }
+
+    Ok(tokens)
+}
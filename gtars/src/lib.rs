@@ -6,6 +6,7 @@
 //!
 pub mod ailist;
 pub mod common;
+pub mod consensus;
 pub mod io;
 pub mod tokenizers;
 pub mod uniwig;
@@ -2,6 +2,9 @@ use anyhow::Result;
 use clap::Command;
 
 // go through the library crate to get the interfaces
+use gtars::ailist;
+use gtars::consensus;
+use gtars::io;
 use gtars::tokenizers;
 // use gtars::uniwig;
 
@@ -19,16 +22,32 @@ fn build_parser() -> Command {
         .about("Performance critical tools for working with genomic interval data with an emphasis on preprocessing for machine learning pipelines.")
         .subcommand_required(true)
         .subcommand(tokenizers::cli::make_tokenization_cli())
+        .subcommand(tokenizers::cli::make_detokenization_cli())
+        .subcommand(ailist::cli::make_overlap_cli())
+        .subcommand(consensus::cli::make_consensus_cli())
+        .subcommand(io::cli::make_gtok_cli())
 }
 
 fn main() -> Result<()> {
     let app = build_parser();
-    let matches = app.get_matches();
+    let matches = app.get_matches_from(ailist::cli::bedtools_style_args(std::env::args_os()));
 
     match matches.subcommand() {
         Some((tokenizers::consts::TOKENIZE_CMD, matches)) => {
             tokenizers::cli::handlers::tokenize_bed_file(matches)?;
         }
+        Some((tokenizers::consts::DETOKENIZE_CMD, matches)) => {
+            tokenizers::cli::handlers::detokenize_gtok_file(matches)?;
+        }
+        Some((ailist::consts::OVERLAP_CMD, matches)) => {
+            ailist::cli::handlers::overlap_bed_files(matches)?;
+        }
+        Some((consensus::consts::CONSENSUS_CMD, matches)) => {
+            consensus::cli::handlers::build_consensus(matches)?;
+        }
+        Some((io::consts::GTOK_CMD, matches)) => {
+            io::cli::handlers::gtok(matches)?;
+        }
 
         _ => unreachable!("Subcommand not found"),
     };
@@ -2,10 +2,10 @@ use std::io;
 use std::io::Write;
 
 use anyhow::Result;
-use clap::{Arg, ArgMatches, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 
 use super::*;
-use crate::common::models::{Region, RegionSet};
+use crate::common::models::Region;
 use crate::tokenizers::TreeTokenizer;
 
 pub fn make_tokenization_cli() -> Command {
@@ -16,7 +16,7 @@ pub fn make_tokenization_cli() -> Command {
             Arg::new("bed")
                 .long("bed")
                 .short('b')
-                .help("Path to the bed file we want to tokenize.")
+                .help("Path to the bed file we want to tokenize. A .gtok file is re-tokenized, see --source-universe.")
                 .required(true),
         )
         .arg(
@@ -26,15 +26,57 @@ pub fn make_tokenization_cli() -> Command {
                 .help("Path to the universe file we want to use.")
                 .required(true),
         )
+        .arg(
+            Arg::new("source-universe")
+                .long("source-universe")
+                .short('s')
+                .help("Path to the universe file the tokens in a .gtok input came from."),
+        )
+        .arg(
+            Arg::new("diagnostics")
+                .long("diagnostics")
+                .help("Instead of tokenizing, report the unknown-token rate on each chromosome and likely chromosome name mismatches.")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("source-universe"),
+        )
+}
+
+pub fn make_detokenization_cli() -> Command {
+    Command::new(consts::DETOKENIZE_CMD)
+        .author("Nathan LeRoy")
+        .about("Decode a .gtok file of token ids back into a bed file. The documents of an indexed file are written one after another, as a single bed file.")
+        .arg(
+            Arg::new("gtok")
+                .long("gtok")
+                .short('g')
+                .help("Path to the .gtok file we want to decode.")
+                .required(true),
+        )
+        .arg(
+            Arg::new("universe")
+                .long("universe")
+                .short('u')
+                .help("Path to the universe file the tokens came from.")
+                .required(true),
+        )
+        .arg(
+            Arg::new("skip-special-tokens")
+                .long("skip-special-tokens")
+                .help("Leave special tokens (unk, pad, ...) out of the output.")
+                .action(ArgAction::SetTrue),
+        )
 }
 
 pub mod handlers {
 
+    use std::ffi::OsStr;
     use std::path::Path;
 
     use anyhow::Context;
 
     use super::*;
+    use crate::common::utils::extract_regions_from_bed_file;
+    use crate::io::{read_gtok_header, read_tokens_from_gtok};
 
     pub fn tokenize_bed_file(matches: &ArgMatches) -> Result<()> {
         let bed = matches
@@ -50,19 +92,101 @@ pub mod handlers {
         let tokenizer = TreeTokenizer::try_from(universe)?;
 
         let bed = Path::new(&bed);
-        let regions = RegionSet::try_from(bed)
-            .with_context(|| "There was an error reading in the bedfile to be tokenized!")?;
-
         let mut stdout = io::stdout().lock();
 
-        let tokenized_regions = tokenizer.tokenize_region_set(&regions);
+        if matches.get_flag("diagnostics") {
+            let regions = extract_regions_from_bed_file(bed)
+                .with_context(|| "There was an error reading in the bedfile to be tokenized!")?;
+            let diagnostics = tokenizer.diagnose(&regions);
+
+            writeln!(
+                stdout,
+                "chr\tregions\tunknown\tin_universe\tsuggested_alias"
+            )?;
+            for chr in diagnostics.chromosomes.iter() {
+                writeln!(
+                    stdout,
+                    "{}\t{}\t{}\t{}\t{}",
+                    chr.chr,
+                    chr.regions,
+                    chr.unknown,
+                    chr.in_universe,
+                    chr.suggested_alias.as_deref().unwrap_or(".")
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        if bed.extension() == Some(OsStr::new("gtok")) {
+            let source = matches
+                .get_one::<String>("source-universe")
+                .with_context(|| "A source universe is required to re-tokenize a .gtok file!")?;
+            let source = TreeTokenizer::try_from(Path::new(&source))?;
+
+            let tokenized_regions = tokenizer.tokenize_gtok(bed, &source)?;
+            for tokenized_region in tokenized_regions.into_iter() {
+                let region: Region = tokenized_region.into();
+                let line = format!("{}\t{}\t{}\n", region.chr, region.start, region.end);
+
+                // push to stdout
+                stdout.write_all(line.as_bytes()).unwrap();
+            }
+
+            return Ok(());
+        }
+
+        let batches = tokenizer.tokenize_from_path(bed, consts::TOKENIZE_BATCH_SIZE)?;
+
+        // stream the bed file so large inputs don't need to fit in memory
+        for tokenized_regions in batches {
+            let tokenized_regions = tokenized_regions
+                .with_context(|| "There was an error reading in the bedfile to be tokenized!")?;
+
+            for tokenized_region in tokenized_regions.into_iter() {
+                let region: Region = tokenized_region.into();
+                let line = format!("{}\t{}\t{}\n", region.chr, region.start, region.end);
+
+                // push to stdout
+                stdout.write_all(line.as_bytes()).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn detokenize_gtok_file(matches: &ArgMatches) -> Result<()> {
+        let gtok = matches
+            .get_one::<String>("gtok")
+            .expect("Gtok file path is required");
+
+        let universe = matches
+            .get_one::<String>("universe")
+            .expect("Universe path is required");
+
+        let skip_special_tokens = matches.get_flag("skip-special-tokens");
+
+        let universe = Path::new(&universe);
+        let tokenizer = TreeTokenizer::try_from(universe)?;
+
+        let header = read_gtok_header(gtok)
+            .with_context(|| "There was an error reading in the gtok file to be decoded!")?;
+        header
+            .check_universe_digest(&tokenizer.universe.digest())
+            .with_context(|| format!("{} wasn't tokenized with this universe", gtok))?;
+
+        // documents aren't separated in the output, they're decoded as one run of tokens
+        let ids = read_tokens_from_gtok(gtok)
+            .with_context(|| "There was an error reading in the gtok file to be decoded!")?;
+        let regions = tokenizer.decode(&ids, skip_special_tokens)?;
+
+        let mut stdout = io::stdout().lock();
 
-        for tokenized_region in tokenized_regions.into_iter() {
-            let region: Region = tokenized_region.into();
+        for region in regions.into_iter() {
             let line = format!("{}\t{}\t{}\n", region.chr, region.start, region.end);
 
             // push to stdout
-            stdout.write_all(line.as_bytes()).unwrap();
+            stdout.write_all(line.as_bytes())?;
         }
 
         Ok(())
@@ -1,7 +1,52 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Debug, PartialEq )]
+use crate::tokenizers::tree_tokenizer::OverlapPolicy;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct TokenizerConfig {
     pub universe: String,
     pub excluderanges: Option<String>,
-}
\ No newline at end of file
+    /// extra special tokens (e.g. `[CLS2]`, cell-type tags), given ids after the built-in ones in this order
+    pub additional_special_tokens: Option<Vec<String>>,
+    /// see `OverlapOptions`
+    pub min_overlap_fraction: Option<f64>,
+    pub min_overlap_bp: Option<u32>,
+    pub overlap_policy: Option<OverlapPolicy>,
+}
+
+impl TryFrom<&Path> for TokenizerConfig {
+    type Error = anyhow::Error;
+    ///
+    /// # Arguments
+    /// - `value` - the path to the yaml config file
+    ///
+    /// # Returns
+    /// A new TokenizerConfig
+    fn try_from(value: &Path) -> Result<Self> {
+        let file = File::open(value).with_context(|| "Failed to open tokenizer config file!")?;
+        let config: TokenizerConfig = serde_yaml::from_reader(BufReader::new(file))
+            .with_context(|| "Failed to parse tokenizer config file!")?;
+
+        Ok(config)
+    }
+}
+
+impl TokenizerConfig {
+    ///
+    /// Write the config to a yaml file.
+    ///
+    /// # Arguments
+    /// - path: the file to write to
+    pub fn to_file(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| "Failed to create tokenizer config file!")?;
+        serde_yaml::to_writer(BufWriter::new(file), self)
+            .with_context(|| "Failed to write tokenizer config file!")?;
+
+        Ok(())
+    }
+}
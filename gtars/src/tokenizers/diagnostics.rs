@@ -0,0 +1,99 @@
+//! Diagnostics for tokenization runs that produce more unknown tokens than expected.
+//!
+//! The most common cause is a naming mismatch between the query regions and the universe,
+//! e.g. `1` vs `chr1`, which silently turns every region on the chromosome into an unknown token.
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+/// Unknown-token counts for the query regions on one chromosome.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChromosomeDiagnostics {
+    pub chr: String,
+    /// the number of query regions on this chromosome
+    pub regions: usize,
+    /// the number of those regions that were tokenized into the unknown token
+    pub unknown: usize,
+    /// whether the universe has any regions on this chromosome
+    pub in_universe: bool,
+    /// a universe chromosome this name likely refers to, if it isn't in the universe itself
+    pub suggested_alias: Option<String>,
+}
+
+impl ChromosomeDiagnostics {
+    pub fn unknown_rate(&self) -> f64 {
+        match self.regions {
+            0 => 0.0,
+            n => self.unknown as f64 / n as f64,
+        }
+    }
+}
+
+/// A summary of how a set of query regions tokenized, broken down by chromosome.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TokenizationDiagnostics {
+    pub regions: usize,
+    pub unknown: usize,
+    /// sorted by number of unknown tokens, most first
+    pub chromosomes: Vec<ChromosomeDiagnostics>,
+}
+
+impl TokenizationDiagnostics {
+    pub fn unknown_rate(&self) -> f64 {
+        match self.regions {
+            0 => 0.0,
+            n => self.unknown as f64 / n as f64,
+        }
+    }
+
+    ///
+    /// The query chromosomes that have a likely alias in the universe, paired with that alias.
+    /// These can be used to rename the query regions before tokenizing again.
+    pub fn suggested_aliases(&self) -> Vec<(String, String)> {
+        self.chromosomes
+            .iter()
+            .filter_map(|chr| {
+                chr.suggested_alias
+                    .as_ref()
+                    .map(|alias| (chr.chr.to_owned(), alias.to_owned()))
+            })
+            .collect()
+    }
+}
+
+///
+/// Find the universe chromosome a query chromosome name most likely refers to. Covers the
+/// `chr` prefix (`1` vs `chr1`), the mitochondrial naming conventions (`MT` vs `chrM`),
+/// and differences in case.
+///
+/// # Arguments
+/// - chr: the query chromosome name, which isn't in the universe
+/// - universe_chrs: the chromosome names in the universe
+pub(crate) fn suggest_alias(chr: &str, universe_chrs: &HashSet<&str>) -> Option<String> {
+    let bare = chr
+        .strip_prefix("chr")
+        .or_else(|| chr.strip_prefix("Chr"))
+        .or_else(|| chr.strip_prefix("CHR"))
+        .unwrap_or(chr);
+
+    let mut candidates = vec![bare.to_string(), format!("chr{}", bare)];
+    if bare.eq_ignore_ascii_case("M") || bare.eq_ignore_ascii_case("MT") {
+        candidates.extend(["chrM", "MT", "chrMT", "M"].map(String::from));
+    }
+
+    candidates
+        .iter()
+        .find(|candidate| candidate.as_str() != chr && universe_chrs.contains(candidate.as_str()))
+        .cloned()
+        .or_else(|| {
+            // fall back to a case-insensitive match on any of the candidates
+            universe_chrs
+                .iter()
+                .find(|universe_chr| {
+                    candidates
+                        .iter()
+                        .any(|candidate| candidate.eq_ignore_ascii_case(universe_chr))
+                })
+                .map(|universe_chr| universe_chr.to_string())
+        })
+}
@@ -0,0 +1,54 @@
+//! Options and results for `EncodePlus`: truncation, padding, and attention masks in one call.
+
+/// Which end of a sequence to drop tokens from when it's longer than `max_length`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TruncationSide {
+    /// keep the first `max_length` tokens
+    #[default]
+    Right,
+    /// keep the last `max_length` tokens
+    Left,
+}
+
+/// How to pad sequences with the pad token.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaddingStrategy {
+    #[default]
+    DoNotPad,
+    /// pad every sequence in a batch to the longest one
+    Longest,
+    /// pad every sequence to `max_length`
+    MaxLength,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncodingOptions {
+    /// truncate sequences to this many tokens; also the target length for `PaddingStrategy::MaxLength`
+    pub max_length: Option<usize>,
+    pub truncation_side: TruncationSide,
+    pub padding: PaddingStrategy,
+    /// also compute the genomic position metadata of each token, see `TokenPositions`
+    pub return_positions: bool,
+}
+
+/// Genomic position metadata for each token of an encoding, for distance-aware position embeddings.
+///
+/// Each token is compared with the nearest genomic token before it in the sequence. Special tokens and
+/// padding are skipped over, and have a distance and flag of 0, as does the first genomic token.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenPositions {
+    /// bases between the end of the previous token and the start of this one, 0 if they overlap
+    /// or are on different chromosomes
+    pub distances: Vec<u32>,
+    /// 1 where a token is on a different chromosome than the previous token, 0 otherwise
+    pub chromosome_changes: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    pub ids: Vec<u32>,
+    /// 1 for real tokens and 0 for padding, the convention most transformer models expect
+    pub attention_mask: Vec<u8>,
+    /// only computed when `EncodingOptions::return_positions` is set
+    pub positions: Option<TokenPositions>,
+}
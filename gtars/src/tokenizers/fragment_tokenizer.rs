@@ -3,7 +3,7 @@ use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufWriter, Write};
 use std::path::Path;
 
-use crate::common::models::{Region, TokenizedRegionSet};
+use crate::common::models::{Region, RegionSet, TokenizedRegionSet};
 use crate::common::utils::get_dynamic_reader;
 use crate::io::consts::{GTOK_HEADER, GTOK_U32_FLAG};
 
@@ -348,4 +348,91 @@ where
             .map(|ids| TokenizedRegionSet::new(ids, self.tokenizer.get_universe()))
             .collect())
     }
+
+    fn group_fragments_by_barcode(
+        fragments_file_path: &Path,
+    ) -> Result<HashMap<String, Vec<Region>>> {
+        let reader = get_dynamic_reader(fragments_file_path)?;
+
+        let mut barcode_regions_map: HashMap<String, Vec<Region>> = HashMap::new();
+
+        for (line_num, line) in reader.lines().enumerate() {
+            let line = line
+                .with_context(|| format!("Failed parsing line {} in fragments file", line_num))?;
+
+            let (chr, start, end, barcode, _read_support) = Self::parse_fragment_file_line(line)
+                .with_context(|| format!("Failed parsing line {} in fragments file", line_num))?;
+
+            barcode_regions_map
+                .entry(barcode)
+                .or_default()
+                .push(Region { chr, start, end });
+        }
+
+        Ok(barcode_regions_map)
+    }
+
+    ///
+    /// Tokenize a fragments file into one token sequence per cell barcode. Like `tokenize_fragments`, this
+    /// keeps every cell in memory, but the barcode of each cell is kept alongside its tokens.
+    ///
+    /// # Arguments
+    /// - `fragments_file_path` - the path to the fragments file
+    /// - `sort` - whether to order each cell's fragments by genomic position (chromosome name, then start)
+    ///   before tokenizing, instead of keeping them in file order. Chromosome names are compared as strings,
+    ///   as `sort -k1,1 -k2,2n` does, so `chr10` comes before `chr2`
+    pub fn tokenize_fragments_by_barcode(
+        &self,
+        fragments_file_path: &Path,
+        sort: bool,
+    ) -> Result<HashMap<String, TokenizedRegionSet<'_>>> {
+        let barcode_regions_map = Self::group_fragments_by_barcode(fragments_file_path)?;
+
+        Ok(barcode_regions_map
+            .into_iter()
+            .map(|(barcode, mut regions)| {
+                if sort {
+                    regions.sort_by(|a, b| {
+                        a.chr
+                            .cmp(&b.chr)
+                            .then(a.start.cmp(&b.start))
+                            .then(a.end.cmp(&b.end))
+                    });
+                }
+
+                let tokens = self
+                    .tokenizer
+                    .tokenize_region_set(&RegionSet::from(regions));
+                (barcode, tokens)
+            })
+            .collect())
+    }
+
+    ///
+    /// Tokenize a fragments file into one `.gtok` file per cell barcode, with each cell's tokens ordered by the
+    /// genomic position of its fragments, with chromosomes in lexicographic order (see `tokenize_fragments_by_barcode`).
+    /// Sorting needs every fragment in memory, so unlike `tokenize_fragments_to_gtoks` this doesn't stream.
+    ///
+    /// # Arguments
+    /// - `fragments_file_path` - the path to the fragments file
+    /// - `out_path` - the folder to write the `.gtok` files to
+    pub fn tokenize_fragments_to_sorted_gtoks(
+        &self,
+        fragments_file_path: &Path,
+        out_path: &Path,
+    ) -> Result<()> {
+        let cells = self.tokenize_fragments_by_barcode(fragments_file_path, true)?;
+
+        for (barcode, tokens) in cells.iter() {
+            let file_name = out_path.join(format!("{}.gtok", barcode));
+            let file_name = file_name
+                .to_str()
+                .with_context(|| format!("{} is not a valid utf-8 path", file_name.display()))?;
+            tokens
+                .to_gtok_file(file_name)
+                .with_context(|| format!("Failed to write gtok file for {}", barcode))?;
+        }
+
+        Ok(())
+    }
 }
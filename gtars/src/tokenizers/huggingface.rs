@@ -0,0 +1,265 @@
+//! Reading and writing tokenizers in the HuggingFace `tokenizer.json` format.
+//!
+//! The universe is stored as a `WordLevel` model whose words are regions written as
+//! `chr:start-end`, split on whitespace. Special tokens are stored as added tokens
+//! (`<unk>`, `<pad>`, ...), so the file can be loaded directly with `tokenizers.Tokenizer`.
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::common::consts::special_tokens::*;
+use crate::common::models::{Region, Universe};
+use crate::tokenizers::special_tokens::SpecialToken;
+use crate::tokenizers::traits::SpecialTokens;
+use crate::tokenizers::TreeTokenizer;
+
+const HF_FORMAT_VERSION: &str = "1.0";
+const HF_MODEL_TYPE: &str = "WordLevel";
+
+#[derive(Serialize, Deserialize)]
+struct HuggingFaceTokenizer {
+    version: String,
+    truncation: Option<Value>,
+    padding: Option<Value>,
+    added_tokens: Vec<AddedToken>,
+    normalizer: Option<Value>,
+    pre_tokenizer: Option<Value>,
+    post_processor: Option<Value>,
+    decoder: Option<Value>,
+    model: WordLevelModel,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddedToken {
+    id: u32,
+    content: String,
+    single_word: bool,
+    lstrip: bool,
+    rstrip: bool,
+    normalized: bool,
+    special: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WordLevelModel {
+    #[serde(rename = "type")]
+    model_type: String,
+    vocab: BTreeMap<String, u32>,
+    unk_token: String,
+}
+
+const SPECIAL_TOKENS: [SpecialToken; 7] = [
+    SpecialToken::Unk,
+    SpecialToken::Pad,
+    SpecialToken::Mask,
+    SpecialToken::Cls,
+    SpecialToken::Bos,
+    SpecialToken::Eos,
+    SpecialToken::Sep,
+];
+
+fn special_token_word(token: &SpecialToken) -> String {
+    format!("<{}>", token.to_string().to_lowercase())
+}
+
+fn special_token_region(token: &SpecialToken) -> Region {
+    let (chr, start, end) = match token {
+        SpecialToken::Unk => (UNKNOWN_CHR, UNKNOWN_START, UNKNOWN_END),
+        SpecialToken::Pad => (PAD_CHR, PAD_START, PAD_END),
+        SpecialToken::Mask => (MASK_CHR, MASK_START, MASK_END),
+        SpecialToken::Cls => (CLS_CHR, CLS_START, CLS_END),
+        SpecialToken::Bos => (BOS_CHR, BOS_START, BOS_END),
+        SpecialToken::Eos => (EOS_CHR, EOS_START, EOS_END),
+        SpecialToken::Sep => (SEP_CHR, SEP_START, SEP_END),
+    };
+
+    Region {
+        chr: chr.to_string(),
+        start: start as u32,
+        end: end as u32,
+    }
+}
+
+fn region_to_word(region: &Region) -> String {
+    format!("{}:{}-{}", region.chr, region.start, region.end)
+}
+
+fn word_to_region(word: &str) -> Result<Region> {
+    let (chr, coords) = word
+        .rsplit_once(':')
+        .with_context(|| format!("Token '{}' is not a chr:start-end region", word))?;
+    let (start, end) = coords
+        .split_once('-')
+        .with_context(|| format!("Token '{}' is not a chr:start-end region", word))?;
+
+    Ok(Region {
+        chr: chr.to_string(),
+        start: start
+            .parse()
+            .with_context(|| format!("Invalid start in token '{}'", word))?,
+        end: end
+            .parse()
+            .with_context(|| format!("Invalid end in token '{}'", word))?,
+    })
+}
+
+impl TreeTokenizer {
+    ///
+    /// Write the tokenizer as a HuggingFace `tokenizer.json` file.
+    ///
+    /// # Arguments
+    /// - path: the file to write to
+    pub fn to_huggingface_json(&self, path: &Path) -> Result<()> {
+        let mut special_words: HashMap<Region, String> = self
+            .special_tokens_map()
+            .into_iter()
+            .map(|(token, region)| (region, special_token_word(&token)))
+            .collect();
+        for region in self.additional_special_tokens() {
+            special_words.insert(region.to_owned(), region.chr.to_owned());
+        }
+
+        let mut vocab: BTreeMap<String, u32> = BTreeMap::new();
+        let mut added_tokens: Vec<AddedToken> = Vec::new();
+
+        for id in 0..self.universe.len() as u32 {
+            let region = self
+                .universe
+                .convert_id_to_region(id)
+                .with_context(|| format!("Universe has no region for token id {}", id))?;
+
+            match special_words.get(&region) {
+                Some(word) => {
+                    added_tokens.push(AddedToken {
+                        id,
+                        content: word.to_owned(),
+                        single_word: false,
+                        lstrip: false,
+                        rstrip: false,
+                        normalized: false,
+                        special: true,
+                    });
+                    vocab.insert(word.to_owned(), id);
+                }
+                None => {
+                    vocab.insert(region_to_word(&region), id);
+                }
+            }
+        }
+
+        let tokenizer = HuggingFaceTokenizer {
+            version: HF_FORMAT_VERSION.to_string(),
+            truncation: None,
+            padding: None,
+            added_tokens,
+            normalizer: None,
+            pre_tokenizer: Some(serde_json::json!({ "type": "WhitespaceSplit" })),
+            post_processor: None,
+            decoder: None,
+            model: WordLevelModel {
+                model_type: HF_MODEL_TYPE.to_string(),
+                vocab,
+                unk_token: special_token_word(&SpecialToken::Unk),
+            },
+        };
+
+        let file = File::create(path).with_context(|| "Failed to create tokenizer.json file!")?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &tokenizer)
+            .with_context(|| "Failed to write tokenizer.json file!")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    ///
+    /// Load a tokenizer from a HuggingFace `tokenizer.json` file written by `to_huggingface_json`.
+    ///
+    /// # Arguments
+    /// - path: the file to read from
+    pub fn from_huggingface_json(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| "Failed to open tokenizer.json file!")?;
+        let tokenizer: HuggingFaceTokenizer = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| "Failed to parse tokenizer.json file!")?;
+
+        if tokenizer.model.model_type != HF_MODEL_TYPE {
+            anyhow::bail!(
+                "Unsupported tokenizer model type: {}",
+                tokenizer.model.model_type
+            );
+        }
+
+        let mut special_regions: HashMap<String, Region> = SPECIAL_TOKENS
+            .iter()
+            .map(|token| (special_token_word(token), special_token_region(token)))
+            .collect();
+
+        // any other special added token is one of the tokenizer's additional special tokens
+        let mut additional_tokens: Vec<(u32, String)> = tokenizer
+            .added_tokens
+            .iter()
+            .filter(|token| token.special && !special_regions.contains_key(&token.content))
+            .map(|token| (token.id, token.content.to_owned()))
+            .collect();
+        additional_tokens.sort();
+        for (_, word) in additional_tokens.iter() {
+            special_regions.insert(
+                word.to_owned(),
+                Region {
+                    chr: word.to_owned(),
+                    start: 0,
+                    end: 0,
+                },
+            );
+        }
+
+        let mut words: Vec<(u32, String)> = tokenizer
+            .model
+            .vocab
+            .into_iter()
+            .map(|(word, id)| (id, word))
+            .collect();
+        words.sort();
+
+        // the genomic regions come first and make up the universe; the special tokens
+        // follow them and are added as tokens only, like a tokenizer built from a bed file
+        let mut regions: Vec<Region> = Vec::with_capacity(words.len());
+        let mut specials: Vec<(u32, Region)> = Vec::new();
+        for (expected_id, (id, word)) in words.iter().enumerate() {
+            if *id as usize != expected_id {
+                anyhow::bail!("Token ids in tokenizer.json must run from 0 with no gaps.");
+            }
+
+            match special_regions.get(word) {
+                Some(region) => specials.push((*id, region.to_owned())),
+                None if specials.is_empty() => regions.push(word_to_region(word)?),
+                None => anyhow::bail!(
+                    "Region token '{}' comes after the special tokens in tokenizer.json.",
+                    word
+                ),
+            }
+        }
+
+        let mut universe = Universe::from(regions);
+        for (id, region) in specials.iter() {
+            universe.insert_token(region);
+            if universe.convert_region_to_id(region) != Some(*id) {
+                anyhow::bail!("Duplicate token '{}' in tokenizer.json.", region.chr);
+            }
+        }
+
+        let mut tokenizer = TreeTokenizer::from(universe);
+        let additional_tokens: Vec<String> = additional_tokens
+            .into_iter()
+            .map(|(_, word)| word)
+            .collect();
+        tokenizer.add_special_tokens(&additional_tokens);
+
+        Ok(tokenizer)
+    }
+}
@@ -2,22 +2,34 @@
 //!
 //! There is currently only one tokenizer - the `TreeTokenizer`
 pub mod cli;
+pub mod config;
+pub mod diagnostics;
+pub mod encoding;
 pub mod fragment_tokenizer;
+pub mod huggingface;
+pub mod multi_resolution_tokenizer;
 pub mod soft_tokenizer;
 pub mod special_tokens;
 pub mod traits;
 pub mod tree_tokenizer;
-pub mod config;
 
 /// constants for the tokenizer module.
 pub mod consts {
     /// command for the `gtars` cli
     pub const TOKENIZE_CMD: &str = "tokenize";
+    pub const DETOKENIZE_CMD: &str = "detokenize";
     pub const UNIVERSE_FILE_NAME: &str = "universe.bed";
+    /// number of regions the `gtars` cli tokenizes at a time
+    pub const TOKENIZE_BATCH_SIZE: usize = 10_000;
+    /// number of documents `TreeTokenizer::tokenize_batch_to_gtok` holds in memory at a time
+    pub const GTOK_DOCUMENT_BATCH_SIZE: usize = 1_024;
 }
 
 // expose the TreeTokenizer struct to users of this crate
+pub use config::TokenizerConfig;
+pub use diagnostics::{ChromosomeDiagnostics, TokenizationDiagnostics};
+pub use encoding::{Encoding, EncodingOptions, PaddingStrategy, TokenPositions, TruncationSide};
 pub use fragment_tokenizer::FragmentTokenizer;
-pub use traits::{SingleCellTokenizer, Tokenizer};
-pub use tree_tokenizer::TreeTokenizer;
-pub use config::TokenizerConfig;
\ No newline at end of file
+pub use multi_resolution_tokenizer::MultiResolutionTokenizer;
+pub use traits::{EncodePlus, SingleCellTokenizer, Tokenizer};
+pub use tree_tokenizer::{OverlapOptions, OverlapPolicy, TreeTokenizer};
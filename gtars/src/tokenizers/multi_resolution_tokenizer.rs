@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::common::models::{Region, RegionSet, TokenizedRegionSet};
+use crate::tokenizers::TreeTokenizer;
+
+use super::Tokenizer;
+
+///
+/// Tokenizes regions into several universes at once, from finest to coarsest
+/// (e.g. 100bp tiles, peaks, topic regions), keeping the levels aligned per region.
+pub struct MultiResolutionTokenizer<T>
+where
+    T: Tokenizer,
+{
+    pub levels: Vec<T>,
+}
+
+impl<T> MultiResolutionTokenizer<T>
+where
+    T: Tokenizer,
+{
+    pub fn new(levels: Vec<T>) -> Self {
+        Self { levels }
+    }
+
+    ///
+    /// Tokenize a region into every level.
+    ///
+    /// # Arguments
+    /// - region: the region to tokenize
+    ///
+    /// # Returns
+    /// - the tokens for each level, in level order
+    pub fn tokenize_region(&self, region: &Region) -> Vec<TokenizedRegionSet<'_>> {
+        self.levels
+            .iter()
+            .map(|tokenizer| tokenizer.tokenize_region(region))
+            .collect()
+    }
+
+    ///
+    /// Tokenize a region set into every level. Unlike `Tokenizer::tokenize_region_set`,
+    /// the tokens aren't flattened, so each region's tokens at one level can be matched
+    /// up with its tokens at every other level.
+    ///
+    /// # Arguments
+    /// - region_set: the regions to tokenize
+    ///
+    /// # Returns
+    /// - for each region, in order, the tokens for each level
+    pub fn tokenize_region_set(&self, region_set: &RegionSet) -> Vec<Vec<TokenizedRegionSet<'_>>> {
+        region_set
+            .into_iter()
+            .map(|region| self.tokenize_region(region))
+            .collect()
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    ///
+    /// The vocab size of each level, in level order.
+    pub fn vocab_sizes(&self) -> Vec<usize> {
+        self.levels
+            .iter()
+            .map(|tokenizer| tokenizer.vocab_size())
+            .collect()
+    }
+}
+
+impl MultiResolutionTokenizer<TreeTokenizer> {
+    ///
+    /// Build one `TreeTokenizer` per universe file.
+    ///
+    /// # Arguments
+    /// - paths: the universe bed files, one per level
+    pub fn from_universe_files(paths: &[&Path]) -> Result<Self> {
+        let levels = paths
+            .iter()
+            .map(|path| {
+                TreeTokenizer::try_from(*path)
+                    .with_context(|| format!("Failed to build tokenizer for {}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(levels))
+    }
+}
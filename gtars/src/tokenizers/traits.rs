@@ -7,6 +7,9 @@ use crate::common::models::region::Region;
 use crate::common::models::region_set::RegionSet;
 use crate::common::models::tokenized_regionset::TokenizedRegionSet;
 use crate::common::models::universe::Universe;
+use crate::tokenizers::encoding::{
+    Encoding, EncodingOptions, PaddingStrategy, TokenPositions, TruncationSide,
+};
 use crate::tokenizers::special_tokens::SpecialToken;
 
 pub trait Tokenizer {
@@ -75,6 +78,19 @@ pub trait SpecialTokens {
 
         map
     }
+    ///
+    /// The ids of every special token, including any the tokenizer adds beyond the standard ones.
+    fn special_token_ids(&self) -> Vec<u32> {
+        vec![
+            self.unknown_token_id(),
+            self.padding_token_id(),
+            self.mask_token_id(),
+            self.cls_token_id(),
+            self.bos_token_id(),
+            self.eos_token_id(),
+            self.sep_token_id(),
+        ]
+    }
 }
 
 pub trait AtttentionMask: SpecialTokens {
@@ -107,6 +123,134 @@ pub trait Pad: SpecialTokens {
     }
 }
 
+pub trait EncodePlus: Tokenizer + SpecialTokens {
+    ///
+    /// Tokenize a region set, then truncate and pad it according to `options`.
+    ///
+    /// # Arguments
+    /// - `region_set` - the region set to encode
+    /// - `options` - the truncation and padding to apply
+    ///
+    /// # Returns
+    /// The token ids, their attention mask, and optionally their genomic positions
+    fn encode_plus(&self, region_set: &RegionSet, options: &EncodingOptions) -> Result<Encoding> {
+        Ok(self
+            .encode_batch_plus(std::slice::from_ref(region_set), options)?
+            .pop()
+            .unwrap())
+    }
+
+    ///
+    /// Tokenize many region sets, then truncate and pad them according to `options`.
+    /// `PaddingStrategy::Longest` pads to the longest sequence in the batch after truncation.
+    /// `PaddingStrategy::MaxLength` needs `max_length` to be set.
+    ///
+    /// # Arguments
+    /// - `region_sets` - the region sets to encode
+    /// - `options` - the truncation and padding to apply
+    ///
+    /// # Returns
+    /// The token ids, attention mask, and optionally genomic positions of each region set, in order
+    fn encode_batch_plus(
+        &self,
+        region_sets: &[RegionSet],
+        options: &EncodingOptions,
+    ) -> Result<Vec<Encoding>> {
+        let mut ids_list: Vec<Vec<u32>> = region_sets
+            .iter()
+            .map(|region_set| self.tokenize_region_set(region_set).ids)
+            .collect();
+
+        if let Some(max_length) = options.max_length {
+            for ids in ids_list.iter_mut() {
+                if ids.len() > max_length {
+                    match options.truncation_side {
+                        TruncationSide::Right => ids.truncate(max_length),
+                        TruncationSide::Left => {
+                            ids.drain(..ids.len() - max_length);
+                        }
+                    }
+                }
+            }
+        }
+
+        let padded_length = match options.padding {
+            PaddingStrategy::DoNotPad => None,
+            PaddingStrategy::Longest => ids_list.iter().map(|ids| ids.len()).max(),
+            PaddingStrategy::MaxLength => match options.max_length {
+                Some(max_length) => Some(max_length),
+                None => anyhow::bail!("Padding to max_length needs max_length to be set."),
+            },
+        };
+        let pad_token = self.padding_token_id();
+
+        let special_ids = self.special_token_ids();
+
+        Ok(ids_list
+            .into_iter()
+            .map(|mut ids| {
+                let mut positions = options
+                    .return_positions
+                    .then(|| self.token_positions(&ids, &special_ids));
+                let mut attention_mask = vec![1; ids.len()];
+                if let Some(padded_length) = padded_length {
+                    ids.resize(padded_length.max(ids.len()), pad_token);
+                    attention_mask.resize(ids.len(), 0);
+                    if let Some(positions) = positions.as_mut() {
+                        positions.distances.resize(ids.len(), 0);
+                        positions.chromosome_changes.resize(ids.len(), 0);
+                    }
+                }
+
+                Encoding {
+                    ids,
+                    attention_mask,
+                    positions,
+                }
+            })
+            .collect())
+    }
+
+    ///
+    /// Compute the genomic distance to the previous token and the chromosome-change flag of each token.
+    /// Special tokens have no position, so they get zeros and don't count as the previous token.
+    ///
+    /// # Arguments
+    /// - `ids` - the token ids, in sequence order
+    /// - `special_ids` - the ids of the special tokens, see `SpecialTokens::special_token_ids`
+    fn token_positions(&self, ids: &[u32], special_ids: &[u32]) -> TokenPositions {
+        let universe = self.get_universe();
+
+        let mut positions = TokenPositions {
+            distances: Vec::with_capacity(ids.len()),
+            chromosome_changes: Vec::with_capacity(ids.len()),
+        };
+        let mut previous: Option<Region> = None;
+
+        for id in ids {
+            let region = match universe.convert_id_to_region(*id) {
+                Some(region) if !special_ids.contains(id) => region,
+                _ => {
+                    positions.distances.push(0);
+                    positions.chromosome_changes.push(0);
+                    continue;
+                }
+            };
+
+            let (distance, change) = match previous.as_ref() {
+                Some(prev) if prev.chr == region.chr => (region.start.saturating_sub(prev.end), 0),
+                Some(_) => (0, 1),
+                None => (0, 0),
+            };
+            positions.distances.push(distance);
+            positions.chromosome_changes.push(change);
+            previous = Some(region);
+        }
+
+        positions
+    }
+}
+
 pub trait FromPretrained: Tokenizer {
     fn from_pretrained(models: &str) -> Result<Self>
     where
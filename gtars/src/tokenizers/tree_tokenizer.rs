@@ -1,17 +1,85 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
 use rust_lapper::{Interval, Lapper};
+use serde::{Deserialize, Serialize};
 
 use crate::common::consts::special_tokens::*;
 use crate::common::models::{Region, RegionSet, TokenizedRegionSet, Universe};
-use crate::common::utils::extract_regions_from_bed_file;
-use crate::tokenizers::traits::{Pad, SpecialTokens, Tokenizer};
+use crate::common::utils::{extract_regions_from_bed_file, stream_regions_from_bed_file};
+use crate::io::consts::{GTOK_U16_FLAG, GTOK_U32_FLAG};
+use crate::io::indexed::{encode_document, GtokDocumentWriter};
+use crate::io::{read_gtok_header, read_tokens_from_gtok};
+use crate::tokenizers::consts::GTOK_DOCUMENT_BATCH_SIZE;
+use crate::tokenizers::diagnostics::{
+    suggest_alias, ChromosomeDiagnostics, TokenizationDiagnostics,
+};
+use crate::tokenizers::traits::{EncodePlus, Pad, SpecialTokens, Tokenizer};
+use crate::tokenizers::TokenizerConfig;
+
+/// Which universe regions a query region is tokenized into when it overlaps several.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    /// every universe region that passes the thresholds
+    #[default]
+    All,
+    /// only the universe region with the largest overlap; ties go to the leftmost region
+    Best,
+}
+
+/// Thresholds a query region must meet before it's assigned a universe region's token.
+/// The default accepts any overlap.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverlapOptions {
+    /// the minimum fraction of the query region (between 0 and 1) that must be overlapped
+    pub min_fraction: f64,
+    /// the minimum number of overlapping base pairs
+    pub min_bp: u32,
+    pub policy: OverlapPolicy,
+}
+
+impl OverlapOptions {
+    fn select<'a>(
+        &self,
+        region: &Region,
+        intervals: impl Iterator<Item = &'a Interval<u32, u32>>,
+    ) -> Vec<u32> {
+        let length = region.end.saturating_sub(region.start);
+
+        let passing = intervals.filter_map(|interval| {
+            let overlap = interval
+                .stop
+                .min(region.end)
+                .saturating_sub(interval.start.max(region.start));
+
+            let passes = overlap >= self.min_bp
+                && (length == 0 || overlap as f64 >= self.min_fraction * length as f64);
+            passes.then_some((interval.val, overlap))
+        });
+
+        match self.policy {
+            OverlapPolicy::All => passing.map(|(id, _)| id).collect(),
+            OverlapPolicy::Best => passing
+                // keep the first of equally good hits
+                .fold(None, |best: Option<(u32, u32)>, hit| match best {
+                    Some(best) if best.1 >= hit.1 => Some(best),
+                    _ => Some(hit),
+                })
+                .map(|(id, _)| id)
+                .into_iter()
+                .collect(),
+        }
+    }
+}
 
 pub struct TreeTokenizer {
     pub universe: Universe,
     tree: HashMap<String, Lapper<u32, u32>>,
+    additional_special_tokens: Vec<Region>,
+    overlap_options: OverlapOptions,
 }
 
 impl TryFrom<&Path> for TreeTokenizer {
@@ -23,57 +91,46 @@ impl TryFrom<&Path> for TreeTokenizer {
     /// # Returns
     /// A new TreeTokenizer
     fn try_from(value: &Path) -> Result<Self> {
-        let mut universe = Universe::try_from(value)?;
-
-        // add special tokens to the universe
-        // unk
-        universe.insert_token(&Region {
-            chr: UNKNOWN_CHR.to_string(),
-            start: UNKNOWN_START as u32,
-            end: UNKNOWN_END as u32,
-        });
+        let universe = Universe::try_from(value)?;
 
-        // pad
-        universe.insert_token(&Region {
-            chr: PAD_CHR.to_string(),
-            start: PAD_START as u32,
-            end: PAD_END as u32,
-        });
-
-        // mask
-        universe.insert_token(&Region {
-            chr: MASK_CHR.to_string(),
-            start: MASK_START as u32,
-            end: MASK_END as u32,
-        });
-
-        // eos
-        universe.insert_token(&Region {
-            chr: EOS_CHR.to_string(),
-            start: EOS_START as u32,
-            end: EOS_END as u32,
-        });
-
-        // bos
-        universe.insert_token(&Region {
-            chr: BOS_CHR.to_string(),
-            start: BOS_START as u32,
-            end: BOS_END as u32,
-        });
+        // the special tokens are added to the universe in `From<Universe>`
+        Ok(TreeTokenizer::from(universe))
+    }
+}
 
-        // cls
-        universe.insert_token(&Region {
-            chr: CLS_CHR.to_string(),
-            start: CLS_START as u32,
-            end: CLS_END as u32,
-        });
+///
+/// The special tokens every tokenizer has, in the order they get ids after the universe regions.
+fn default_special_tokens() -> [Region; 7] {
+    [
+        (UNKNOWN_CHR, UNKNOWN_START, UNKNOWN_END),
+        (PAD_CHR, PAD_START, PAD_END),
+        (MASK_CHR, MASK_START, MASK_END),
+        (EOS_CHR, EOS_START, EOS_END),
+        (BOS_CHR, BOS_START, BOS_END),
+        (CLS_CHR, CLS_START, CLS_END),
+        (SEP_CHR, SEP_START, SEP_END),
+    ]
+    .map(|(chr, start, end)| Region {
+        chr: chr.to_string(),
+        start: start as u32,
+        end: end as u32,
+    })
+}
 
-        // sep
-        universe.insert_token(&Region {
-            chr: SEP_CHR.to_string(),
-            start: SEP_START as u32,
-            end: SEP_END as u32,
-        });
+impl From<Universe> for TreeTokenizer {
+    ///
+    /// # Arguments
+    /// - `universe` - the universe to tokenize into; any special tokens it doesn't have yet are
+    ///   added after its regions
+    ///
+    /// # Returns
+    /// A new TreeTokenizer
+    fn from(mut universe: Universe) -> Self {
+        for region in default_special_tokens().iter() {
+            if universe.convert_region_to_id(region).is_none() {
+                universe.insert_token(region);
+            }
+        }
 
         let mut tree: HashMap<String, Lapper<u32, u32>> = HashMap::new();
         let mut intervals: HashMap<String, Vec<Interval<u32, u32>>> = HashMap::new();
@@ -98,67 +155,22 @@ impl TryFrom<&Path> for TreeTokenizer {
             tree.insert(chr.to_string(), lapper);
         }
 
-        Ok(TreeTokenizer { universe, tree })
+        TreeTokenizer {
+            universe,
+            tree,
+            additional_special_tokens: Vec::new(),
+            overlap_options: OverlapOptions::default(),
+        }
     }
 }
 
 impl Tokenizer for TreeTokenizer {
     fn tokenize_region(&self, region: &Region) -> TokenizedRegionSet {
-        let lapper = self.tree.get(&region.chr);
-        match lapper {
-            Some(lapper) => {
-                let intervals = lapper.find(region.start, region.end);
-                let ids: Vec<u32> = intervals.map(|interval| interval.val).collect();
-
-                if ids.is_empty() {
-                    let ids = vec![self.unknown_token_id()];
-                    return TokenizedRegionSet {
-                        ids,
-                        universe: &self.universe,
-                    };
-                }
-
-                TokenizedRegionSet {
-                    ids,
-                    universe: &self.universe,
-                }
-            }
-            None => TokenizedRegionSet {
-                ids: vec![self.unknown_token_id()],
-                universe: &self.universe,
-            },
-        }
+        self.tokenize_regions(std::slice::from_ref(region))
     }
 
     fn tokenize_region_set(&self, region_set: &RegionSet) -> TokenizedRegionSet {
-        let mut tokenized_regions: Vec<u32> = Vec::new();
-
-        for region in region_set {
-            let lapper = self.tree.get(&region.chr);
-
-            match lapper {
-                Some(tree) => {
-                    let intervals = tree.find(region.start, region.end);
-
-                    let regions: Vec<u32> = intervals.map(|interval| interval.val).collect();
-
-                    if regions.is_empty() {
-                        tokenized_regions.push(self.unknown_token_id());
-                        continue;
-                    }
-
-                    tokenized_regions.extend(regions);
-                }
-                None => {
-                    tokenized_regions.push(self.unknown_token_id());
-                }
-            }
-        }
-
-        TokenizedRegionSet {
-            ids: tokenized_regions,
-            universe: &self.universe,
-        }
+        self.tokenize_regions(&region_set.regions)
     }
 
     fn vocab_size(&self) -> usize {
@@ -268,16 +280,364 @@ impl SpecialTokens for TreeTokenizer {
             .convert_region_to_id(&self.sep_token())
             .unwrap()
     }
+
+    fn special_token_ids(&self) -> Vec<u32> {
+        self.special_tokens_map()
+            .values()
+            .chain(self.additional_special_tokens.iter())
+            .filter_map(|region| self.universe.convert_region_to_id(region))
+            .collect()
+    }
 }
 
 impl TreeTokenizer {
+    ///
+    /// Tokenize a slice of regions, without needing to own them in a `RegionSet` first.
+    fn tokenize_regions(&self, regions: &[Region]) -> TokenizedRegionSet<'_> {
+        let mut tokenized_regions: Vec<u32> = Vec::new();
+
+        for region in regions {
+            let lapper = self.tree.get(&region.chr);
+
+            match lapper {
+                Some(tree) => {
+                    let intervals = tree.find(region.start, region.end);
+
+                    let regions: Vec<u32> = self.overlap_options.select(region, intervals);
+
+                    if regions.is_empty() {
+                        tokenized_regions.push(self.unknown_token_id());
+                        continue;
+                    }
+
+                    tokenized_regions.extend(regions);
+                }
+                None => {
+                    tokenized_regions.push(self.unknown_token_id());
+                }
+            }
+        }
+
+        TokenizedRegionSet {
+            ids: tokenized_regions,
+            universe: &self.universe,
+        }
+    }
+
+    ///
+    /// Tokenize many documents at once, spreading them across threads with rayon.
+    ///
+    /// # Arguments
+    /// - documents: the regions of each document
+    ///
+    /// # Returns
+    /// - the tokens of each document, in the same order as `documents`
+    pub fn tokenize_batch(&self, documents: &[Vec<Region>]) -> Vec<TokenizedRegionSet<'_>> {
+        documents
+            .par_iter()
+            .map(|regions| self.tokenize_regions(regions))
+            .collect()
+    }
+
+    ///
+    /// Tokenize many documents at once and write them to a single indexed `.gtok` file,
+    /// tagged with the universe digest. Documents are tokenized in parallel a batch at a
+    /// time and written in order as each batch finishes, so only one batch of tokens is
+    /// held in memory.
+    ///
+    /// # Arguments
+    /// - documents: the regions of each document
+    /// - path: the `.gtok` file to write the documents to
+    pub fn tokenize_batch_to_gtok(&self, documents: &[Vec<Region>], path: &Path) -> Result<()> {
+        // every id is below the vocab size, so the token width is known before tokenizing
+        let flag = match self.vocab_size() <= u16::MAX as usize + 1 {
+            true => GTOK_U16_FLAG,
+            false => GTOK_U32_FLAG,
+        };
+        let digest = self.universe.digest();
+        let mut writer = GtokDocumentWriter::create(path, flag, Some(&digest), None)?;
+
+        for batch in documents.chunks(GTOK_DOCUMENT_BATCH_SIZE) {
+            let encoded = batch
+                .par_iter()
+                .map(|regions| {
+                    let ids = self.tokenize_regions(regions).ids;
+                    Ok((encode_document(&ids, flag, None)?, ids.len() as u64))
+                })
+                .collect::<Result<Vec<(Vec<u8>, u64)>>>()?;
+
+            for (bytes, num_tokens) in encoded {
+                writer.write_encoded(&bytes, num_tokens)?;
+            }
+        }
+
+        writer.finish()
+    }
+
+    ///
+    /// Convert token ids back into the regions they stand for.
+    ///
+    /// # Arguments
+    /// - ids: the token ids to decode
+    /// - skip_special_tokens: whether to drop special tokens (unk, pad, ..., and any additional ones) from the output
+    ///
+    /// # Returns
+    /// - the decoded regions, in order
+    pub fn decode(&self, ids: &[u32], skip_special_tokens: bool) -> Result<Vec<Region>> {
+        let mut special_tokens: HashSet<Region> = self.special_tokens_map().into_values().collect();
+        special_tokens.extend(self.additional_special_tokens.iter().cloned());
+
+        let mut regions: Vec<Region> = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let region = self
+                .universe
+                .convert_id_to_region(id)
+                .with_context(|| format!("Token id {} is not in the vocabulary", id))?;
+
+            if skip_special_tokens && special_tokens.contains(&region) {
+                continue;
+            }
+            regions.push(region);
+        }
+
+        Ok(regions)
+    }
+
+    ///
+    /// Build a tokenizer from a yaml config file (see `TokenizerConfig`). A relative universe
+    /// path is resolved against the directory the config file is in.
+    ///
+    /// # Arguments
+    /// - config_path: the path to the config file
+    pub fn from_config(config_path: &Path) -> Result<Self> {
+        let config = TokenizerConfig::try_from(config_path)?;
+
+        let universe_path = config_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&config.universe);
+        let mut tokenizer = TreeTokenizer::try_from(universe_path.as_path())
+            .with_context(|| format!("Failed to read universe {}", universe_path.display()))?;
+
+        if let Some(tokens) = &config.additional_special_tokens {
+            tokenizer.add_special_tokens(tokens);
+        }
+
+        tokenizer.set_overlap_options(OverlapOptions {
+            min_fraction: config.min_overlap_fraction.unwrap_or(0.0),
+            min_bp: config.min_overlap_bp.unwrap_or(0),
+            policy: config.overlap_policy.unwrap_or_default(),
+        });
+
+        Ok(tokenizer)
+    }
+
+    ///
+    /// Add extra, domain-specific special tokens (e.g. `[CLS2]`, cell-type tags) to the vocabulary.
+    /// New tokens get the next free ids in the order given and tokens that are already special keep
+    /// theirs, so the same config always produces the same ids.
+    ///
+    /// # Arguments
+    /// - tokens: the names of the tokens to add
+    pub fn add_special_tokens(&mut self, tokens: &[String]) {
+        for token in tokens {
+            let region = Region {
+                chr: token.to_owned(),
+                start: 0,
+                end: 0,
+            };
+
+            if self.additional_special_tokens.contains(&region) {
+                continue;
+            }
+            if self.universe.convert_region_to_id(&region).is_none() {
+                self.universe.insert_token(&region);
+            }
+            self.additional_special_tokens.push(region);
+        }
+    }
+
+    ///
+    /// Require a minimum overlap before a query region is assigned a universe region's token,
+    /// and choose between keeping every passing region or only the best one. Regions with
+    /// no passing overlap become the unknown token.
+    ///
+    /// # Arguments
+    /// - options: the thresholds and policy to tokenize with
+    pub fn set_overlap_options(&mut self, options: OverlapOptions) {
+        self.overlap_options = options;
+    }
+
+    pub fn overlap_options(&self) -> &OverlapOptions {
+        &self.overlap_options
+    }
+
+    ///
+    /// The additional special tokens, in the order they were added.
+    pub fn additional_special_tokens(&self) -> &[Region] {
+        &self.additional_special_tokens
+    }
+
+    ///
+    /// Get the id of an additional special token by name.
+    pub fn additional_special_token_id(&self, token: &str) -> Option<u32> {
+        self.additional_special_tokens
+            .iter()
+            .find(|region| region.chr == token)
+            .and_then(|region| self.universe.convert_region_to_id(region))
+    }
+
     pub fn tokenize_bed_file(&self, bed_file: &Path) -> Result<TokenizedRegionSet> {
         let regions = extract_regions_from_bed_file(bed_file)?;
         let rs = RegionSet::from(regions);
 
         Ok(self.tokenize_region_set(&rs))
     }
+
+    ///
+    /// Tokenize a stream of regions in batches, so inputs never have to be held in memory
+    /// all at once. A read error is yielded in place of the batch it occurred in.
+    ///
+    /// # Arguments
+    /// - regions: the regions to tokenize
+    /// - batch_size: the number of regions to tokenize per batch
+    ///
+    /// # Returns
+    /// - an iterator over the tokens of each batch, in input order
+    pub fn tokenize_iter<'a, I>(
+        &'a self,
+        regions: I,
+        batch_size: usize,
+    ) -> impl Iterator<Item = Result<TokenizedRegionSet<'a>>> + 'a
+    where
+        I: IntoIterator<Item = Result<Region>>,
+        I::IntoIter: 'a,
+    {
+        let batch_size = batch_size.max(1);
+        let mut regions = regions.into_iter();
+
+        std::iter::from_fn(move || {
+            let mut batch: Vec<Region> = Vec::with_capacity(batch_size);
+
+            for region in regions.by_ref() {
+                match region {
+                    Ok(region) => batch.push(region),
+                    Err(e) => return Some(Err(e)),
+                }
+                if batch.len() == batch_size {
+                    break;
+                }
+            }
+
+            if batch.is_empty() {
+                return None;
+            }
+
+            Some(Ok(self.tokenize_region_set(&RegionSet::from(batch))))
+        })
+    }
+
+    ///
+    /// Stream a (possibly gzipped) bed file and tokenize it in batches.
+    ///
+    /// # Arguments
+    /// - bed_file: the path to the bed file
+    /// - batch_size: the number of regions to tokenize per batch
+    ///
+    /// # Returns
+    /// - an iterator over the tokens of each batch, in file order
+    pub fn tokenize_from_path(
+        &self,
+        bed_file: &Path,
+        batch_size: usize,
+    ) -> Result<impl Iterator<Item = Result<TokenizedRegionSet<'_>>> + '_> {
+        let regions = stream_regions_from_bed_file(bed_file)?;
+        Ok(self.tokenize_iter(regions, batch_size))
+    }
+
+    ///
+    /// Tokenize regions and report how many became unknown tokens on each chromosome, with
+    /// suggested aliases for chromosome names that aren't in the universe (e.g. `1` for `chr1`).
+    ///
+    /// # Arguments
+    /// - regions: the query regions
+    ///
+    /// # Returns
+    /// - the per-chromosome summary
+    pub fn diagnose(&self, regions: &[Region]) -> TokenizationDiagnostics {
+        let unknown_id = self.unknown_token_id();
+        let universe_chrs: HashSet<&str> = self.tree.keys().map(|chr| chr.as_str()).collect();
+
+        let mut chromosomes: HashMap<&str, ChromosomeDiagnostics> = HashMap::new();
+        for region in regions {
+            let diagnostics = chromosomes.entry(region.chr.as_str()).or_insert_with(|| {
+                let in_universe = universe_chrs.contains(region.chr.as_str());
+                ChromosomeDiagnostics {
+                    chr: region.chr.to_owned(),
+                    regions: 0,
+                    unknown: 0,
+                    in_universe,
+                    suggested_alias: (!in_universe)
+                        .then(|| suggest_alias(&region.chr, &universe_chrs))
+                        .flatten(),
+                }
+            });
+
+            diagnostics.regions += 1;
+            if self.tokenize_region(region).ids == [unknown_id] {
+                diagnostics.unknown += 1;
+            }
+        }
+
+        let mut chromosomes: Vec<ChromosomeDiagnostics> = chromosomes.into_values().collect();
+        chromosomes.sort_by(|a, b| b.unknown.cmp(&a.unknown).then(a.chr.cmp(&b.chr)));
+
+        TokenizationDiagnostics {
+            regions: regions.len(),
+            unknown: chromosomes.iter().map(|chr| chr.unknown).sum(),
+            chromosomes,
+        }
+    }
+
+    ///
+    /// Re-tokenize a `.gtok` file into this tokenizer's universe. The ids are decoded with
+    /// the tokenizer that wrote the file, and special tokens are dropped since they don't
+    /// stand for genomic regions. Files whose header records a different universe than the
+    /// source tokenizer's are rejected.
+    ///
+    /// # Arguments
+    /// - gtok_file: the path to the `.gtok` file
+    /// - source: the tokenizer whose universe the ids in the file come from
+    ///
+    /// # Returns
+    /// - the tokens of the decoded regions in this tokenizer's universe
+    pub fn tokenize_gtok(
+        &self,
+        gtok_file: &Path,
+        source: &TreeTokenizer,
+    ) -> Result<TokenizedRegionSet<'_>> {
+        let path = gtok_file
+            .to_str()
+            .with_context(|| format!("Invalid path: {}", gtok_file.display()))?;
+        let header = read_gtok_header(path)
+            .with_context(|| format!("Failed to read {}", gtok_file.display()))?;
+        header
+            .check_universe_digest(&source.universe.digest())
+            .with_context(|| {
+                format!(
+                    "{} wasn't tokenized with the source universe",
+                    gtok_file.display()
+                )
+            })?;
+
+        let ids = read_tokens_from_gtok(path)
+            .with_context(|| format!("Failed to read {}", gtok_file.display()))?;
+        let regions = source.decode(&ids, true)?;
+
+        Ok(self.tokenize_region_set(&RegionSet::from(regions)))
+    }
 }
 
 // use default implementation
 impl Pad for TreeTokenizer {}
+impl EncodePlus for TreeTokenizer {}
@@ -3,9 +3,24 @@ use std::path::Path;
 use rstest::*;
 use tempfile::NamedTempFile;
 
-use gtars::common::models::{Region, RegionSet};
-use gtars::io::{append_tokens_to_gtok_file, init_gtok_file, read_tokens_from_gtok};
-use gtars::tokenizers::{Tokenizer, TreeTokenizer};
+use gtars::ailist::{annotate, intersect, subtract, union, AIList, Interval, MultiChromOverlapper};
+use gtars::common::models::annotation::extract_annotations_from_file;
+use gtars::common::models::variant::{extract_variants_from_file, VariantRecord};
+use gtars::common::models::{
+    AnnotationFormat, AnnotationRecord, BedRecord, Region, RegionSet, Strand, Universe,
+};
+use gtars::consensus::{build_consensus_universe, ConsensusMethod};
+use gtars::io::{
+    append_gtok_documents, append_tokens_to_gtok_file, concat_gtok_files, init_gtok_file,
+    read_gtok_document, read_gtok_document_at, read_gtok_header, read_gtok_manifest,
+    read_tokens_from_gtok, write_gtok_documents, write_tokens_to_gtok, GtokReader, MmapGtokReader,
+    ShardedGtokWriter,
+};
+use gtars::tokenizers::traits::SpecialTokens;
+use gtars::tokenizers::{
+    EncodePlus, EncodingOptions, FragmentTokenizer, MultiResolutionTokenizer, OverlapOptions,
+    OverlapPolicy, PaddingStrategy, Tokenizer, TokenizerConfig, TreeTokenizer, TruncationSide,
+};
 
 #[fixture]
 fn path_to_data() -> &'static str {
@@ -50,7 +65,10 @@ fn path_to_gtok_file() -> &'static str {
 mod tests {
     use std::io::Read;
 
-    use gtars::common::utils::extract_regions_from_bed_file;
+    use gtars::common::sweep::sweep_overlaps;
+    use gtars::common::utils::{
+        extract_regions_from_bed_file, merge_regions_with, stream_regions_from_bed_file,
+    };
 
     use super::*;
 
@@ -129,6 +147,469 @@ mod tests {
         assert!(rs2.len() == 25);
     }
 
+    #[rstest]
+    fn test_region_set_identifier(path_to_bed_file: &str) {
+        let path = Path::new(path_to_bed_file);
+        let rs = RegionSet::try_from(path).unwrap();
+
+        assert_eq!(rs.identifier(), "e400b6705b9dc3cac1db75e82aab8287");
+
+        // the same regions built in memory should give the same identifier
+        let rs2 = RegionSet::from(rs.regions.clone());
+        assert_eq!(rs2.identifier(), rs.identifier());
+    }
+
+    #[rstest]
+    fn test_merge_regions_with() {
+        let region = |chr: &str, start: u32, end: u32| Region {
+            chr: chr.to_string(),
+            start,
+            end,
+        };
+        let regions = vec![
+            (region("chr1", 100, 200), 1.0),
+            (region("chr2", 0, 50), 4.0),
+            (region("chr1", 150, 250), 2.0),
+            (region("chr1", 260, 300), 3.0),
+        ];
+
+        let merged = merge_regions_with(regions.clone(), 0, |a, b| a + b);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0], (region("chr1", 100, 250), 3.0));
+
+        let merged = merge_regions_with(regions, 10, f64::max);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0], (region("chr1", 100, 300), 3.0));
+        assert_eq!(merged[1], (region("chr2", 0, 50), 4.0));
+    }
+
+    #[rstest]
+    fn test_region_set_merge(path_to_bed_file: &str) {
+        let rs = RegionSet::try_from(Path::new(path_to_bed_file)).unwrap();
+
+        // several peaks sit within a few hundred bases of each other
+        assert_eq!(rs.merge(0).len(), 25);
+        assert!(rs.merge(100).len() < 25);
+    }
+
+    #[rstest]
+    fn test_sweep_overlaps(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let mut a = extract_regions_from_bed_file(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let mut b = extract_regions_from_bed_file(Path::new(path_to_bed_file)).unwrap();
+        a.sort_by(|x, y| (&x.chr, x.start).cmp(&(&y.chr, y.start)));
+        b.sort_by(|x, y| (&x.chr, x.start).cmp(&(&y.chr, y.start)));
+
+        let overlaps = sweep_overlaps(a.into_iter().map(Ok), b.into_iter().map(Ok))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(overlaps.len(), 3);
+        for (a, b) in overlaps.iter() {
+            assert_eq!(a.chr, b.chr);
+            assert!(a.start < b.end && b.start < a.end);
+        }
+    }
+
+    #[rstest]
+    fn test_sweep_overlaps_unsorted(path_to_bed_file: &str) {
+        let b = stream_regions_from_bed_file(Path::new(path_to_bed_file)).unwrap();
+        let a = vec![Ok(Region {
+            chr: "chr9".to_string(),
+            start: 3526000,
+            end: 3526200,
+        })];
+
+        let res = sweep_overlaps(a, b).collect::<anyhow::Result<Vec<_>>>();
+        assert!(res.is_err());
+    }
+
+    #[rstest]
+    fn test_ailist_count_and_any() {
+        let mut intervals: Vec<Interval> = (0..100)
+            .map(|i| Interval {
+                start: i * 10,
+                end: i * 10 + 25,
+            })
+            .collect();
+        let ailist = AIList::new(&mut intervals, 3);
+
+        let query = Interval {
+            start: 500,
+            end: 540,
+        };
+        assert_eq!(ailist.count(&query), ailist.query(&query).len());
+        assert!(ailist.any(&query));
+
+        let query = Interval {
+            start: 5000,
+            end: 5100,
+        };
+        assert_eq!(ailist.count(&query), 0);
+        assert!(!ailist.any(&query));
+    }
+
+    #[rstest]
+    fn test_ailist_closest() {
+        let mut intervals = vec![
+            Interval { start: 0, end: 10 },
+            Interval { start: 20, end: 30 },
+            Interval { start: 50, end: 60 },
+        ];
+        let ailist = AIList::new(&mut intervals, 3);
+
+        let query = Interval { start: 35, end: 40 };
+        let hits = ailist.closest_interval(&query, 2);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].0.start, hits[0].1), (20, -5));
+        assert_eq!((hits[1].0.start, hits[1].1), (50, 10));
+
+        let (nearest, distance) = ailist.closest(45).unwrap();
+        assert_eq!((nearest.start, distance), (50, 4));
+
+        let (nearest, distance) = ailist.closest(25).unwrap();
+        assert_eq!((nearest.start, distance), (20, 0));
+    }
+
+    #[rstest]
+    fn test_ailist_merged_and_coverage() {
+        let mut intervals = vec![
+            Interval { start: 0, end: 10 },
+            Interval { start: 5, end: 15 },
+            Interval { start: 15, end: 20 },
+            Interval { start: 30, end: 40 },
+            Interval { start: 32, end: 35 },
+        ];
+        let ailist = AIList::new(&mut intervals, 3);
+
+        let merged = ailist.merged();
+        assert_eq!(merged.len(), 2);
+        assert_eq!((merged[0].start, merged[0].end), (0, 20));
+        assert_eq!((merged[1].start, merged[1].end), (30, 40));
+
+        assert_eq!(ailist.coverage(), 30);
+    }
+
+    #[rstest]
+    fn test_ailist_query_with_min_overlap() {
+        let mut intervals = vec![
+            Interval { start: 0, end: 100 },
+            Interval {
+                start: 90,
+                end: 200,
+            },
+            Interval { start: 40, end: 60 },
+        ];
+        let ailist = AIList::new(&mut intervals, 3);
+
+        let query = Interval {
+            start: 50,
+            end: 100,
+        };
+        assert_eq!(ailist.query(&query).len(), 3);
+
+        // [90, 200) and [40, 60) each only cover 20% of the query
+        let hits = ailist.query_with_min_overlap(&query, 0.5, false);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end), (0, 100));
+
+        // [90, 200) covers 20% of the query, but its 10 overlapping bases are under 20% of its own length
+        let hits = ailist.query_with_min_overlap(&query, 0.2, true);
+        assert_eq!(hits.len(), 2);
+
+        // `query` returns [40, 60) for a query starting at 60, but they share no bases
+        let touching = Interval { start: 60, end: 80 };
+        assert_eq!(ailist.query(&touching).len(), 2);
+        assert_eq!(
+            ailist.query_with_min_overlap(&touching, 0.0, false).len(),
+            1
+        );
+
+        // an empty query has nothing to cover, so every hit is kept
+        let empty = Interval { start: 50, end: 50 };
+        assert_eq!(ailist.query_with_min_overlap(&empty, 0.5, false).len(), 2);
+    }
+
+    #[rstest]
+    fn test_ailist_set_operations() {
+        let a = vec![
+            Interval { start: 0, end: 100 },
+            Interval {
+                start: 200,
+                end: 300,
+            },
+        ];
+        let b = || {
+            vec![
+                Interval { start: 10, end: 20 },
+                Interval { start: 15, end: 30 },
+                Interval {
+                    start: 90,
+                    end: 210,
+                },
+            ]
+        };
+        let index = AIList::new(&mut b(), 3);
+
+        let bounds = |intervals: Vec<Interval>| -> Vec<(u32, u32)> {
+            intervals.iter().map(|i| (i.start, i.end)).collect()
+        };
+
+        assert_eq!(
+            bounds(intersect(&a, &index)),
+            vec![(10, 30), (90, 100), (200, 210)]
+        );
+        assert_eq!(
+            bounds(subtract(&a, &index)),
+            vec![(0, 10), (30, 90), (210, 300)]
+        );
+
+        assert_eq!(bounds(union(&a, &b())), vec![(0, 300)]);
+    }
+
+    #[rstest]
+    fn test_multi_chrom_overlapper(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let overlapper =
+            MultiChromOverlapper::from_bed_path(Path::new(path_to_bed_file), 3).unwrap();
+
+        let rs = RegionSet::try_from(Path::new(path_to_bed_file)).unwrap();
+        let from_rs = MultiChromOverlapper::from_region_set(&rs, 3);
+        assert_eq!(
+            overlapper.chromosomes().count(),
+            from_rs.chromosomes().count()
+        );
+
+        let queries = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let counts: Vec<usize> = queries
+            .regions
+            .iter()
+            .map(|r| overlapper.count(r))
+            .collect();
+        assert_eq!(counts, vec![1, 2, 0]);
+
+        let hits = overlapper.query(&queries.regions[1]);
+        assert_eq!(hits.len(), 2);
+        assert!(!overlapper.any(&Region {
+            chr: "chrUn".to_string(),
+            start: 0,
+            end: 100,
+        }));
+    }
+
+    #[rstest]
+    fn test_query_batch(path_to_bed_file: &str) {
+        let overlapper =
+            MultiChromOverlapper::from_bed_path(Path::new(path_to_bed_file), 3).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_bed_file)).unwrap();
+
+        let hits = overlapper.query_batch(&rs.regions);
+        let par_hits = overlapper.par_query_batch(&rs.regions);
+
+        assert_eq!(hits.len(), 25);
+        for ((region, hits), par_hits) in rs.regions.iter().zip(hits).zip(par_hits) {
+            assert!(!hits.is_empty());
+            assert_eq!(hits.len(), par_hits.len());
+            assert_eq!(hits.len(), overlapper.count(region));
+        }
+    }
+
+    #[rstest]
+    fn test_query_ids(path_to_bed_file: &str) {
+        let mut intervals = vec![
+            Interval { start: 50, end: 60 },
+            Interval { start: 0, end: 20 },
+            Interval { start: 10, end: 30 },
+        ];
+        let ailist = AIList::new(&mut intervals, 3);
+        let mut ids = ailist.query_ids(&Interval { start: 15, end: 55 });
+        ids.sort();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert_eq!(
+            ailist.query_ids(&Interval { start: 35, end: 40 }),
+            Vec::<usize>::new()
+        );
+
+        let rs = RegionSet::try_from(Path::new(path_to_bed_file)).unwrap();
+        let overlapper = MultiChromOverlapper::from_region_set(&rs, 3);
+        for (i, region) in rs.regions.iter().enumerate() {
+            let ids = overlapper.query_ids(region);
+            assert!(ids.contains(&i));
+            assert_eq!(ids.len(), overlapper.count(region));
+            for id in ids {
+                assert_eq!(rs.regions[id].chr, region.chr);
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_window_counts(path_to_bed_file: &str) {
+        let mut intervals = vec![
+            Interval { start: 0, end: 10 },
+            Interval { start: 5, end: 25 },
+            Interval { start: 40, end: 45 },
+        ];
+        let ailist = AIList::new(&mut intervals, 3);
+        // like `query`, an interval ending exactly at a window start still counts
+        assert_eq!(
+            ailist.window_counts(50, 10, 10).unwrap(),
+            vec![2, 2, 1, 0, 1]
+        );
+
+        // overlapping windows agree with one query per window
+        let counts = ailist.window_counts(50, 20, 5).unwrap();
+        assert_eq!(counts.len(), 10);
+        for (i, count) in counts.into_iter().enumerate() {
+            let start = i as u32 * 5;
+            let window = Interval {
+                start,
+                end: (start + 20).min(50),
+            };
+            assert_eq!(count, ailist.count(&window));
+        }
+
+        let overlapper =
+            MultiChromOverlapper::from_bed_path(Path::new(path_to_bed_file), 3).unwrap();
+        let counts = overlapper
+            .window_counts("chr15", 100_000_000, 1_000_000, 1_000_000)
+            .unwrap();
+        assert_eq!(counts.len(), 100);
+        assert_eq!(
+            overlapper
+                .window_counts("chrUn", 100_000_000, 1_000_000, 1_000_000)
+                .unwrap(),
+            vec![0; 100]
+        );
+        assert!(ailist.window_counts(50, 0, 10).is_err());
+        assert!(ailist.window_counts(50, 10, 0).is_err());
+        assert!(overlapper.window_counts("chrUn", 50, 10, 0).is_err());
+    }
+
+    #[rstest]
+    fn test_annotate() {
+        let region = |chr: &str, start: u32, end: u32| Region {
+            chr: chr.to_string(),
+            start,
+            end,
+        };
+        let genes = vec![
+            (region("chr1", 0, 100), "GENE1"),
+            (region("chr1", 90, 1000), "GENE2"),
+            (region("chr2", 0, 100), "GENE3"),
+        ];
+        let peaks = vec![
+            region("chr1", 50, 95),
+            region("chr1", 100, 110),
+            region("chr3", 0, 10),
+            region("chr2", 50, 50),
+        ];
+
+        let annotated = annotate(&peaks, &genes, 0.0, false);
+        assert_eq!(annotated.len(), 4);
+        assert_eq!(annotated[0].1, vec!["GENE1", "GENE2"]);
+        // bed ends are exclusive, so this doesn't touch GENE1
+        assert_eq!(annotated[1].1, vec!["GENE2"]);
+        assert!(annotated[2].1.is_empty());
+        // zero-length regions are kept, as in `query_with_min_overlap`
+        assert_eq!(annotated[3].1, vec!["GENE3"]);
+
+        // only GENE1 covers half of the first peak
+        let annotated = annotate(&peaks, &genes, 0.5, false);
+        assert_eq!(annotated[0].1, vec!["GENE1"]);
+
+        // but it doesn't get half of GENE1 back
+        let annotated = annotate(&peaks, &genes, 0.5, true);
+        assert!(annotated[0].1.is_empty());
+    }
+
+    #[rstest]
+    fn test_overlap_cli() {
+        use gtars::ailist::cli::{bedtools_style_args, handlers::write_overlaps, make_overlap_cli};
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let a = tmp_dir.path().join("a.bed");
+        let b = tmp_dir.path().join("b.bed");
+        std::fs::write(&a, "chr1\t10\t20\nchr2\t0\t5\n").unwrap();
+        // touching the A region at either end isn't an overlap, and the hits are
+        // listed out of position order to check that they come out in file order
+        std::fs::write(&b, "chr1\t15\t25\nchr1\t20\t30\nchr1\t5\t12\nchr1\t0\t10\n").unwrap();
+
+        let run = |flags: &[&str]| -> String {
+            let mut args = vec![
+                "gtars",
+                "overlap",
+                "-a",
+                a.to_str().unwrap(),
+                "-b",
+                b.to_str().unwrap(),
+            ];
+            args.extend(flags);
+            let args = bedtools_style_args(args.into_iter().map(std::ffi::OsString::from));
+            // the subcommand stands in for the program name
+            let matches = make_overlap_cli().get_matches_from(args.into_iter().skip(1));
+
+            let mut out = Vec::new();
+            write_overlaps(&matches, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(run(&[]), "chr1\t15\t20\nchr1\t10\t12\n");
+        assert_eq!(
+            run(&["-wa", "-wb"]),
+            "chr1\t10\t20\tchr1\t15\t25\nchr1\t10\t20\tchr1\t5\t12\n"
+        );
+        assert_eq!(run(&["-c"]), "chr1\t10\t20\t2\nchr2\t0\t5\t0\n");
+        assert_eq!(run(&["-v"]), "chr2\t0\t5\n");
+
+        // other subcommands keep their arguments, even ones that look like bedtools flags
+        let args = ["gtars", "tokenize", "-b", "-wa", "-u", "-wb"];
+        let rewritten = bedtools_style_args(args.iter().map(std::ffi::OsString::from));
+        assert_eq!(rewritten, args.map(std::ffi::OsString::from));
+    }
+
+    #[rstest]
+    fn test_save_and_load_overlapper(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let overlapper =
+            MultiChromOverlapper::from_bed_path(Path::new(path_to_bed_file), 3).unwrap();
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let tmp_path = tmp_file.into_temp_path();
+        overlapper.save(&tmp_path).unwrap();
+        let loaded = MultiChromOverlapper::load(&tmp_path).unwrap();
+
+        let queries = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        for query in queries.regions.iter() {
+            assert_eq!(loaded.count(query), overlapper.count(query));
+        }
+
+        // a single AIList round trips on its own, too
+        let ailist = loaded.get("chr15").unwrap();
+        ailist.save(&tmp_path).unwrap();
+        let reloaded = AIList::load(&tmp_path).unwrap();
+        assert_eq!(reloaded.coverage(), ailist.coverage());
+        let chr15 = Interval {
+            start: 0,
+            end: u32::MAX,
+        };
+        assert_eq!(reloaded.query_ids(&chr15), ailist.query_ids(&chr15));
+
+        // and the two formats can't be mixed up
+        assert!(MultiChromOverlapper::load(&tmp_path).is_err());
+
+        // corrupt lengths and component offsets are errors, not panics
+        let bytes = std::fs::read(&tmp_path).unwrap();
+        let mut huge = bytes.clone();
+        huge[5..13].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&tmp_path, &huge).unwrap();
+        assert!(AIList::load(&tmp_path).is_err());
+
+        let mut shifted = bytes.clone();
+        shifted[13..21].copy_from_slice(&1u64.to_le_bytes());
+        std::fs::write(&tmp_path, &shifted).unwrap();
+        assert!(AIList::load(&tmp_path).is_err());
+
+        std::fs::write(&tmp_path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(AIList::load(&tmp_path).is_err());
+    }
+
     #[rstest]
     fn test_create_tokenizer(path_to_bed_file: &str) {
         let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
@@ -164,6 +645,1052 @@ mod tests {
         assert!(unknown_token.chr == "chrUNK");
     }
 
+    #[rstest]
+    fn test_tokenize_from_path(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let expected = tokenizer.tokenize_region_set(&rs);
+
+        let batches: Vec<Vec<u32>> = tokenizer
+            .tokenize_from_path(Path::new(path_to_tokenize_bed_file), 2)
+            .unwrap()
+            .map(|batch| batch.unwrap().ids)
+            .collect();
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches.concat(), expected.ids);
+    }
+
+    #[rstest]
+    fn test_huggingface_round_trip(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+
+        let tmp_file = NamedTempFile::new().unwrap();
+        let tmp_path = tmp_file.into_temp_path();
+        tokenizer.to_huggingface_json(&tmp_path).unwrap();
+
+        let loaded = TreeTokenizer::from_huggingface_json(&tmp_path).unwrap();
+        assert_eq!(loaded.vocab_size(), tokenizer.vocab_size());
+        assert_eq!(
+            loaded.universe.regions.len(),
+            tokenizer.universe.regions.len()
+        );
+        assert_eq!(loaded.universe.digest(), tokenizer.universe.digest());
+
+        // a tokenizer built straight from a universe gets the special tokens too
+        let bare = TreeTokenizer::from(Universe::from(tokenizer.universe.regions.clone()));
+        assert_eq!(bare.vocab_size(), tokenizer.vocab_size());
+        let unknown = Region {
+            chr: "chrFOO".to_string(),
+            start: 0,
+            end: 10,
+        };
+        assert_eq!(
+            bare.tokenize_region(&unknown).ids,
+            vec![bare.unknown_token_id()]
+        );
+        assert_eq!(loaded.unknown_token_id(), tokenizer.unknown_token_id());
+        assert_eq!(loaded.padding_token_id(), tokenizer.padding_token_id());
+
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        assert_eq!(
+            loaded.tokenize_region_set(&rs).ids,
+            tokenizer.tokenize_region_set(&rs).ids
+        );
+    }
+
+    #[rstest]
+    fn test_multi_resolution_tokenizer(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        // a coarser universe made by merging nearby peaks
+        let peaks = RegionSet::try_from(Path::new(path_to_bed_file)).unwrap();
+        let tmp_file = NamedTempFile::new().unwrap();
+        let coarse_path = tmp_file.into_temp_path();
+        peaks.merge(1_000_000).to_bed(&coarse_path).unwrap();
+
+        let tokenizer = MultiResolutionTokenizer::from_universe_files(&[
+            Path::new(path_to_bed_file),
+            &coarse_path,
+        ])
+        .unwrap();
+        assert_eq!(tokenizer.num_levels(), 2);
+        let vocab_sizes = tokenizer.vocab_sizes();
+        assert_eq!(vocab_sizes[0], 32);
+        assert!(vocab_sizes[1] < vocab_sizes[0]);
+
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let tokens = tokenizer.tokenize_region_set(&rs);
+        assert_eq!(tokens.len(), rs.len());
+        for region_tokens in tokens.iter() {
+            assert_eq!(region_tokens.len(), 2);
+        }
+
+        // the two chr15 peaks hit by the second region collapse into a single coarse token
+        assert_eq!(tokens[1][0].len(), 2);
+        assert_eq!(tokens[1][1].len(), 1);
+    }
+
+    #[rstest]
+    fn test_tokenize_batch(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let documents = vec![rs.regions.clone(), rs.regions[..1].to_vec(), vec![]];
+
+        let tokenized = tokenizer.tokenize_batch(&documents);
+        assert_eq!(tokenized.len(), 3);
+        assert_eq!(tokenized[0].ids, tokenizer.tokenize_region_set(&rs).ids);
+        assert_eq!(tokenized[1].len(), 1);
+        assert!(tokenized[2].is_empty());
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("batch.gtok");
+        tokenizer.tokenize_batch_to_gtok(&documents, &path).unwrap();
+        let filename = path.to_str().unwrap();
+
+        let header = read_gtok_header(filename).unwrap();
+        assert_eq!(header.num_documents(), documents.len());
+        assert_eq!(
+            header.universe_digest,
+            Some(tokenizer.get_universe().digest())
+        );
+        for (i, tokens) in tokenized.iter().enumerate() {
+            assert_eq!(read_gtok_document_at(filename, i).unwrap(), tokens.ids);
+        }
+    }
+
+    #[rstest]
+    fn test_decode(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let tokenized = tokenizer.tokenize_region_set(&rs);
+
+        let regions = tokenizer.decode(&tokenized.ids, false).unwrap();
+        assert_eq!(regions.len(), 4);
+        assert_eq!(regions[0].chr, "chr13");
+        assert_eq!(regions[3], tokenizer.unknown_token());
+
+        // the unknown token is dropped
+        let regions = tokenizer.decode(&tokenized.ids, true).unwrap();
+        assert_eq!(regions.len(), 3);
+
+        assert!(tokenizer.decode(&[u32::MAX], false).is_err());
+    }
+
+    #[rstest]
+    fn test_tokenize_fragments_by_barcode(path_to_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let tokenizer = FragmentTokenizer::new(tokenizer);
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let fragments = tmp_dir.path().join("fragments.tsv");
+        std::fs::write(
+            &fragments,
+            "chr15\t49155940\t49155950\tcellA\t1\n\
+             chr15\t49155860\t49155870\tcellB\t1\n\
+             chr13\t74550100\t74550200\tcellA\t2\n",
+        )
+        .unwrap();
+
+        let universe = tokenizer.tokenizer.get_universe();
+        let chr13 = universe
+            .convert_chr_start_end_to_id("chr13", 74550022, 74550411)
+            .unwrap();
+        let chr15 = universe
+            .convert_chr_start_end_to_id("chr15", 49155935, 49156182)
+            .unwrap();
+
+        let cells = tokenizer
+            .tokenize_fragments_by_barcode(&fragments, false)
+            .unwrap();
+        assert_eq!(cells.len(), 2);
+        assert_eq!(cells["cellA"].ids, vec![chr15, chr13]);
+        assert_eq!(cells["cellB"].len(), 1);
+
+        let cells = tokenizer
+            .tokenize_fragments_by_barcode(&fragments, true)
+            .unwrap();
+        assert_eq!(cells["cellA"].ids, vec![chr13, chr15]);
+
+        let out_path = tmp_dir.path().join("gtoks");
+        tokenizer
+            .tokenize_fragments_to_sorted_gtoks(&fragments, &out_path)
+            .unwrap();
+        let cell_a = out_path.join("cellA.gtok");
+        assert_eq!(
+            read_tokens_from_gtok(cell_a.to_str().unwrap()).unwrap(),
+            vec![chr13, chr15]
+        );
+        assert!(out_path.join("cellB.gtok").exists());
+    }
+
+    #[rstest]
+    fn test_tokenizer_from_config(path_to_bed_file: &str) {
+        // the fixture's universe path is relative to the config file
+        let tokenizer = TreeTokenizer::from_config(Path::new("tests/data/tokenizer.yaml")).unwrap();
+        assert_eq!(tokenizer.vocab_size(), 32);
+        assert!(tokenizer.additional_special_tokens().is_empty());
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let config_path = tmp_dir.path().join("tokenizer.yaml");
+        let config = TokenizerConfig {
+            universe: std::fs::canonicalize(path_to_bed_file)
+                .unwrap()
+                .display()
+                .to_string(),
+            excluderanges: None,
+            additional_special_tokens: Some(vec!["[CLS2]".to_string(), "[T_CELL]".to_string()]),
+            min_overlap_fraction: None,
+            min_overlap_bp: Some(100),
+            overlap_policy: Some(OverlapPolicy::Best),
+        };
+        config.to_file(&config_path).unwrap();
+        assert_eq!(
+            TokenizerConfig::try_from(config_path.as_path()).unwrap(),
+            config
+        );
+
+        let mut tokenizer = TreeTokenizer::from_config(&config_path).unwrap();
+        assert_eq!(tokenizer.vocab_size(), 34);
+        assert_eq!(tokenizer.overlap_options().min_bp, 100);
+        assert_eq!(tokenizer.overlap_options().policy, OverlapPolicy::Best);
+        assert_eq!(tokenizer.additional_special_token_id("[CLS2]"), Some(32));
+        assert_eq!(tokenizer.additional_special_token_id("[T_CELL]"), Some(33));
+        assert_eq!(tokenizer.additional_special_token_id("[B_CELL]"), None);
+
+        // adding a token twice doesn't move it
+        tokenizer.add_special_tokens(&["[B_CELL]".to_string(), "[CLS2]".to_string()]);
+        assert_eq!(tokenizer.vocab_size(), 35);
+        assert_eq!(tokenizer.additional_special_token_id("[CLS2]"), Some(32));
+
+        let regions = tokenizer.decode(&[0, 32, 34], true).unwrap();
+        assert_eq!(regions.len(), 1);
+
+        // additional tokens survive a round trip through tokenizer.json
+        let hf_path = tmp_dir.path().join("tokenizer.json");
+        tokenizer.to_huggingface_json(&hf_path).unwrap();
+        let loaded = TreeTokenizer::from_huggingface_json(&hf_path).unwrap();
+        assert_eq!(loaded.vocab_size(), 35);
+        assert_eq!(loaded.additional_special_token_id("[B_CELL]"), Some(34));
+    }
+
+    #[rstest]
+    fn test_encode_plus(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let ids = tokenizer.tokenize_region_set(&rs).ids;
+        let pad = tokenizer.padding_token_id();
+
+        // no options is plain tokenization
+        let encoding = tokenizer
+            .encode_plus(&rs, &EncodingOptions::default())
+            .unwrap();
+        assert_eq!(encoding.ids, ids);
+        assert_eq!(encoding.attention_mask, vec![1; 4]);
+
+        let encoding = tokenizer
+            .encode_plus(
+                &rs,
+                &EncodingOptions {
+                    max_length: Some(2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(encoding.ids, ids[..2]);
+
+        let encoding = tokenizer
+            .encode_plus(
+                &rs,
+                &EncodingOptions {
+                    max_length: Some(2),
+                    truncation_side: TruncationSide::Left,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(encoding.ids, ids[2..]);
+
+        let encoding = tokenizer
+            .encode_plus(
+                &rs,
+                &EncodingOptions {
+                    max_length: Some(6),
+                    padding: PaddingStrategy::MaxLength,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(encoding.ids[..4], ids);
+        assert_eq!(encoding.ids[4..], [pad, pad]);
+        assert_eq!(encoding.attention_mask, vec![1, 1, 1, 1, 0, 0]);
+
+        // there's nothing to pad to
+        let short = RegionSet::from(rs.regions[..1].to_vec());
+        assert!(tokenizer
+            .encode_plus(
+                &short,
+                &EncodingOptions {
+                    padding: PaddingStrategy::MaxLength,
+                    ..Default::default()
+                },
+            )
+            .is_err());
+
+        let encodings = tokenizer
+            .encode_batch_plus(
+                &[rs, short],
+                &EncodingOptions {
+                    padding: PaddingStrategy::Longest,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(encodings[0].attention_mask, vec![1; 4]);
+        assert_eq!(encodings[1].ids.len(), 4);
+        assert_eq!(encodings[1].attention_mask, vec![1, 0, 0, 0]);
+        assert!(encodings[0].positions.is_none());
+    }
+
+    #[rstest]
+    fn test_tokenize_gtok(path_to_bed_file: &str, path_to_tokenize_bed_file: &str) {
+        let source = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let target = TreeTokenizer::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+        let rs = RegionSet::try_from(Path::new(path_to_tokenize_bed_file)).unwrap();
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let gtok = tmp_dir.path().join("tokens.gtok");
+        let mut ids = source.tokenize_region_set(&rs).ids;
+        ids.push(source.unknown_token_id());
+        write_tokens_to_gtok(gtok.to_str().unwrap(), &ids).unwrap();
+
+        // the unknown token is dropped, the rest land in the target universe
+        let retokenized = target.tokenize_gtok(&gtok, &source).unwrap();
+        let expected = target
+            .tokenize_region_set(&RegionSet::from(source.decode(&ids, true).unwrap()))
+            .ids;
+        assert_eq!(retokenized.ids, expected);
+        assert!(!retokenized.ids.contains(&target.unknown_token_id()));
+
+        // a file from another universe can't be decoded with the source
+        let digest = source.universe.digest();
+        write_gtok_documents(gtok.to_str().unwrap(), &[ids.clone()], Some(&digest)).unwrap();
+        assert_eq!(target.tokenize_gtok(&gtok, &source).unwrap().ids, expected);
+        let digest = target.universe.digest();
+        write_gtok_documents(gtok.to_str().unwrap(), &[ids], Some(&digest)).unwrap();
+        assert!(target.tokenize_gtok(&gtok, &source).is_err());
+    }
+
+    #[rstest]
+    fn test_tokenization_diagnostics(path_to_bed_file: &str) {
+        let tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let region = |chr: &str, start: u32, end: u32| Region {
+            chr: chr.to_string(),
+            start,
+            end,
+        };
+        let regions = vec![
+            region("chr2", 203871200, 203871375),
+            region("chr2", 1, 2),
+            region("17", 7915738, 7915777),
+            region("17", 7915738, 7915777),
+            region("MT", 1, 2),
+            region("scaffold_1", 1, 2),
+        ];
+
+        let diagnostics = tokenizer.diagnose(&regions);
+        assert_eq!(diagnostics.regions, 6);
+        assert_eq!(diagnostics.unknown, 5);
+
+        let chromosomes: Vec<(&str, usize, bool)> = diagnostics
+            .chromosomes
+            .iter()
+            .map(|chr| (chr.chr.as_str(), chr.unknown, chr.in_universe))
+            .collect();
+        assert_eq!(
+            chromosomes,
+            vec![
+                ("17", 2, false),
+                ("MT", 1, false),
+                ("chr2", 1, true),
+                ("scaffold_1", 1, false)
+            ]
+        );
+        assert_eq!(diagnostics.chromosomes[2].unknown_rate(), 0.5);
+        assert_eq!(
+            diagnostics.suggested_aliases(),
+            vec![("17".to_string(), "chr17".to_string())]
+        );
+    }
+
+    #[rstest]
+    fn test_gtok_v2_documents(path_to_bed_file: &str) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let universe = Universe::try_from(Path::new(path_to_bed_file)).unwrap();
+        let digest = universe.digest();
+
+        let docs: Vec<Vec<u32>> = vec![vec![1, 2, 3], vec![], vec![70_000, 4]];
+        let path = tmp_dir.path().join("docs.gtok");
+        let path = path.to_str().unwrap();
+        write_gtok_documents(path, &docs, Some(&digest)).unwrap();
+
+        let header = read_gtok_header(path).unwrap();
+        assert_eq!(header.version, 2);
+        assert_eq!(header.token_width, 4);
+        assert_eq!(header.universe_digest, Some(digest));
+        assert_eq!(header.num_documents(), 3);
+        assert_eq!(header.num_tokens(), 5);
+
+        for (i, doc) in docs.iter().enumerate().rev() {
+            assert_eq!(&read_gtok_document_at(path, i).unwrap(), doc);
+        }
+        assert!(read_gtok_document_at(path, 3).is_err());
+
+        // the flat reader still works, and v1 files read as a single document
+        assert_eq!(
+            read_tokens_from_gtok(path).unwrap(),
+            vec![1, 2, 3, 70_000, 4]
+        );
+        assert!(append_tokens_to_gtok_file(path, &[1]).is_err());
+
+        // corrupt indexes are errors, not panics or huge allocations
+        let bytes = std::fs::read(path).unwrap();
+        // magic, v2 flag, version, size flag, and digest length come before the digest,
+        // which is followed by the offset of the index at the end of the file
+        let pointer = 9 + u16::from_le_bytes([bytes[7], bytes[8]]) as usize;
+        let index = u64::from_le_bytes(bytes[pointer..pointer + 8].try_into().unwrap()) as usize;
+        let corrupt = tmp_dir.path().join("corrupt.gtok");
+        let corrupt = corrupt.to_str().unwrap();
+
+        let mut outside = bytes.clone();
+        outside[pointer..pointer + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(corrupt, &outside).unwrap();
+        assert!(read_gtok_header(corrupt).is_err());
+
+        let mut huge = bytes.clone();
+        huge[index..index + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(corrupt, &huge).unwrap();
+        assert!(read_gtok_header(corrupt).is_err());
+
+        let mut decreasing = bytes.clone();
+        decreasing[index + 16..index + 24].copy_from_slice(&9u64.to_le_bytes());
+        std::fs::write(corrupt, &decreasing).unwrap();
+        assert!(read_gtok_header(corrupt).is_err());
+
+        std::fs::write(corrupt, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(read_gtok_header(corrupt).is_err());
+
+        let v1 = tmp_dir.path().join("v1.gtok");
+        let v1 = v1.to_str().unwrap();
+        write_tokens_to_gtok(v1, &[7, 8]).unwrap();
+        let header = read_gtok_header(v1).unwrap();
+        assert_eq!((header.version, header.num_documents()), (1, 1));
+        assert_eq!(read_gtok_document_at(v1, 0).unwrap(), vec![7, 8]);
+    }
+
+    #[rstest]
+    fn test_gtok_readers() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let docs: Vec<Vec<u32>> = vec![vec![1, 2, 3], vec![], vec![4, 5]];
+        let path = tmp_dir.path().join("docs.gtok");
+        write_gtok_documents(path.to_str().unwrap(), &docs, None).unwrap();
+
+        let reader = GtokReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.header().universe_digest, None);
+        let read: Vec<Vec<u32>> = reader.documents().map(|doc| doc.unwrap()).collect();
+        assert_eq!(read, docs);
+
+        let mmap = MmapGtokReader::open(&path).unwrap();
+        assert_eq!(mmap.document(2).unwrap(), docs[2]);
+        assert_eq!(mmap.document(0).unwrap(), docs[0]);
+        assert!(mmap.document(3).is_err());
+
+        // v1 files are a single document
+        let v1 = tmp_dir.path().join("v1.gtok");
+        write_tokens_to_gtok(v1.to_str().unwrap(), &[7, 8]).unwrap();
+        let read: Vec<Vec<u32>> = GtokReader::open(&v1)
+            .unwrap()
+            .documents()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(read, vec![vec![7, 8]]);
+        assert_eq!(
+            MmapGtokReader::open(&v1).unwrap().document(0).unwrap(),
+            vec![7, 8]
+        );
+    }
+
+    #[rstest]
+    fn test_concat_and_append_gtok() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| tmp_dir.path().join(name);
+
+        write_gtok_documents(
+            path("a.gtok").to_str().unwrap(),
+            &[vec![1], vec![2, 3]],
+            Some("u1"),
+        )
+        .unwrap();
+        write_tokens_to_gtok(path("b.gtok").to_str().unwrap(), &[70_000, 4]).unwrap();
+        write_gtok_documents(path("c.gtok").to_str().unwrap(), &[vec![5]], Some("u2")).unwrap();
+
+        write_gtok_documents(path("d.gtok").to_str().unwrap(), &[vec![9]], None).unwrap();
+
+        let a = path("a.gtok");
+        let b = path("b.gtok");
+        let d = path("d.gtok");
+        let header = concat_gtok_files(&[&b, &d], &path("bd.gtok")).unwrap();
+        assert_eq!(header.num_documents(), 2);
+        assert_eq!(header.token_width, 4);
+        assert_eq!(header.universe_digest, None);
+        let bd = path("bd.gtok");
+        let bd = bd.to_str().unwrap();
+        assert_eq!(read_gtok_document_at(bd, 0).unwrap(), vec![70_000, 4]);
+
+        // different universes don't mix, and neither do files with and without a digest
+        assert!(concat_gtok_files(&[&a, &path("c.gtok")], &path("ac.gtok")).is_err());
+        assert!(concat_gtok_files(&[&a, &b], &path("ab.gtok")).is_err());
+
+        // the output can't be an input, however it's spelled
+        let alias = tmp_dir.path().join(".").join("d.gtok");
+        assert!(concat_gtok_files(&[&b, &d], &alias).is_err());
+        assert_eq!(
+            read_gtok_document_at(d.to_str().unwrap(), 0).unwrap(),
+            vec![9]
+        );
+
+        let a = a.to_str().unwrap();
+        let before = std::fs::read(a).unwrap();
+        append_gtok_documents(a, &[vec![6, 7], vec![]], 0).unwrap();
+        let header = read_gtok_header(a).unwrap();
+        assert_eq!(header.num_documents(), 4);
+        assert_eq!(header.universe_digest.as_deref(), Some("u1"));
+        assert_eq!(header.token_width, 2);
+        assert_eq!(read_gtok_document_at(a, 2).unwrap(), vec![6, 7]);
+        assert_eq!(read_tokens_from_gtok(a).unwrap(), vec![1, 2, 3, 6, 7]);
+        // the append happened in place: the old tokens are where they were
+        let after = std::fs::read(a).unwrap();
+        let tokens = header.data_start as usize..header.data_start as usize + 3 * 2;
+        assert_eq!(before[tokens.clone()], after[tokens]);
+        // and the copy of the old index, kept while the new one was written, is gone
+        assert_eq!(after.len() as u64, header.index_start + 8 + 8 * 5);
+
+        // wider tokens rewrite the file once
+        append_gtok_documents(a, &[vec![80_000]], 0).unwrap();
+        let header = read_gtok_header(a).unwrap();
+        assert_eq!((header.num_documents(), header.token_width), (5, 4));
+        assert_eq!(
+            read_tokens_from_gtok(a).unwrap(),
+            vec![1, 2, 3, 6, 7, 80_000]
+        );
+        // no temporary files are left behind
+        assert_eq!(std::fs::read_dir(tmp_dir.path()).unwrap().count(), 5);
+
+        // appending to a v1 file keeps its tokens as the first document
+        append_gtok_documents(b.to_str().unwrap(), &[vec![8]], 0).unwrap();
+        assert_eq!(
+            read_gtok_document_at(b.to_str().unwrap(), 1).unwrap(),
+            vec![8]
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[rstest]
+    fn test_compressed_gtok_documents() {
+        use gtars::io::write_compressed_gtok_documents;
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("docs.gtok");
+        let docs: Vec<Vec<u32>> = vec![vec![1; 1000], vec![], (0..500).collect()];
+        write_compressed_gtok_documents(path.to_str().unwrap(), &docs, Some("u1"), 3).unwrap();
+
+        let header = read_gtok_header(path.to_str().unwrap()).unwrap();
+        assert!(header.compressed);
+        assert_eq!(header.num_tokens(), 1500);
+        assert!(std::fs::metadata(&path).unwrap().len() < 1500);
+
+        assert_eq!(
+            MmapGtokReader::open(&path).unwrap().document(2).unwrap(),
+            docs[2]
+        );
+        let read: Vec<Vec<u32>> = GtokReader::open(&path)
+            .unwrap()
+            .documents()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(read, docs);
+        assert_eq!(
+            read_tokens_from_gtok(path.to_str().unwrap()).unwrap(),
+            docs.concat()
+        );
+
+        // appending keeps the file compressed
+        let path = path.to_str().unwrap();
+        append_gtok_documents(path, &[vec![7; 100]], 3).unwrap();
+        let header = read_gtok_header(path).unwrap();
+        assert!(header.compressed);
+        assert_eq!(header.num_documents(), 4);
+        assert_eq!(read_gtok_document_at(path, 3).unwrap(), vec![7; 100]);
+        assert_eq!(read_gtok_document_at(path, 2).unwrap(), docs[2]);
+        let path = Path::new(path);
+
+        // concatenating decompresses
+        let out = tmp_dir.path().join("out.gtok");
+        let header = concat_gtok_files(&[path], &out).unwrap();
+        assert!(!header.compressed);
+        assert_eq!(
+            read_gtok_document_at(out.to_str().unwrap(), 0).unwrap(),
+            docs[0]
+        );
+    }
+
+    #[rstest]
+    fn test_sharded_gtok_writer() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let dir = tmp_dir.path().join("corpus");
+
+        let docs: Vec<Vec<u32>> = vec![
+            vec![1, 2, 3],
+            vec![4, 5],
+            vec![70_000; 6],
+            vec![],
+            vec![9],
+            vec![6, 7, 8],
+            vec![10, 11],
+            vec![12],
+        ];
+        let mut writer = ShardedGtokWriter::new(&dir, 5).unwrap();
+        assert_eq!(writer.write(&docs[0]).unwrap(), 0);
+        assert_eq!(writer.write_batch(&docs[1..5]).unwrap(), vec![1, 2, 3, 4]);
+        // keeps filling the shard the batch left open
+        assert_eq!(writer.write(&docs[5]).unwrap(), 5);
+        assert_eq!(writer.write_batch(&docs[6..]).unwrap(), vec![6, 7]);
+        let manifest = writer.finish().unwrap();
+
+        let placement: Vec<(&str, u64)> = manifest
+            .iter()
+            .map(|entry| (entry.shard.as_str(), entry.index))
+            .collect();
+        assert_eq!(
+            placement,
+            vec![
+                ("shard_00000.gtok", 0),
+                ("shard_00000.gtok", 1),
+                // longer than the limit, so it gets a shard of its own
+                ("shard_00001.gtok", 0),
+                ("shard_00002.gtok", 0),
+                ("shard_00002.gtok", 1),
+                ("shard_00002.gtok", 2),
+                ("shard_00003.gtok", 0),
+                ("shard_00003.gtok", 1),
+            ]
+        );
+
+        // shards are indexed gtok files
+        let shard = dir.join("shard_00002.gtok");
+        let header = read_gtok_header(shard.to_str().unwrap()).unwrap();
+        assert_eq!(header.version, 2);
+        assert_eq!(header.num_documents(), 3);
+        assert_eq!(
+            read_tokens_from_gtok(shard.to_str().unwrap()).unwrap(),
+            vec![9, 6, 7, 8]
+        );
+
+        let manifest = read_gtok_manifest(&dir).unwrap();
+        for (entry, doc) in manifest.iter().zip(docs.iter()).rev() {
+            assert_eq!(&read_gtok_document(&dir, entry).unwrap(), doc);
+        }
+
+        // an entry that doesn't match its shard is an error, not a wrong read
+        let mut entry = manifest[1].clone();
+        entry.index = 5;
+        assert!(read_gtok_document(&dir, &entry).is_err());
+        entry.index = 0;
+        assert!(read_gtok_document(&dir, &entry).is_err());
+    }
+
+    #[rstest]
+    fn test_encode_plus_positions(path_to_bed_file: &str) {
+        let mut tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let region = |chr: &str, start: u32, end: u32| Region {
+            chr: chr.to_string(),
+            start,
+            end,
+        };
+        let rs = RegionSet::from(vec![
+            region("chr2", 203871200, 203871375),
+            region("chr2", 203871387, 203871588),
+            region("chrX", 1, 2),
+            region("chr17", 7915738, 7915777),
+        ]);
+
+        let encoding = tokenizer
+            .encode_plus(
+                &rs,
+                &EncodingOptions {
+                    max_length: Some(6),
+                    padding: PaddingStrategy::MaxLength,
+                    return_positions: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let positions = encoding.positions.unwrap();
+
+        // the unknown token in the middle is skipped when finding the previous token
+        assert_eq!(encoding.ids[2], tokenizer.unknown_token_id());
+        assert_eq!(positions.distances, vec![0, 12, 0, 0, 0, 0]);
+        assert_eq!(positions.chromosome_changes, vec![0, 0, 0, 1, 0, 0]);
+
+        // special tokens are known by id, additional ones included
+        tokenizer.add_special_tokens(&["[T_CELL]".to_string()]);
+        let t_cell = tokenizer.additional_special_token_id("[T_CELL]").unwrap();
+        let special_ids = tokenizer.special_token_ids();
+        assert_eq!(special_ids.len(), 8);
+        assert!(special_ids.contains(&t_cell));
+        assert!(special_ids.contains(&tokenizer.unknown_token_id()));
+    }
+
+    #[rstest]
+    fn test_prune_universe_by_count(path_to_bed_file: &str) {
+        let universe = Universe::try_from(Path::new(path_to_bed_file)).unwrap();
+        let chr13 = universe
+            .convert_chr_start_end_to_id("chr13", 74550022, 74550411)
+            .unwrap();
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp_dir.path().join("a.bed"),
+            "chr13\t74550100\t74550200\nchr15\t49155940\t49155950\n",
+        )
+        .unwrap();
+        // touches chr15:49155935-49156182 only at its (exclusive) end
+        std::fs::write(tmp_dir.path().join("b.bed"), "chr15\t49156182\t49156190\n").unwrap();
+        let gtok = tmp_dir.path().join("c.gtok");
+        write_tokens_to_gtok(gtok.to_str().unwrap(), &[chr13, 10_000]).unwrap();
+        std::fs::write(tmp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let (pruned, dropped) = universe.prune_by_count(tmp_dir.path(), 2).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned.regions[0].chr, "chr13");
+        assert_eq!(dropped.len(), universe.len() - 1);
+        assert!(dropped
+            .iter()
+            .any(|(region, count)| region.start == 49155935 && *count == 1));
+
+        let (pruned, _) = universe.prune_by_count(tmp_dir.path(), 0).unwrap();
+        assert_eq!(pruned.len(), universe.len());
+
+        // special tokens aren't regions, but they survive pruning
+        let mut with_special = universe.clone();
+        let unk = Region {
+            chr: "chrUNK".to_string(),
+            start: 0,
+            end: 0,
+        };
+        with_special.insert_token(&unk);
+        let (pruned, _) = with_special.prune_by_count(tmp_dir.path(), 2).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(pruned.convert_region_to_id(&unk), Some(1));
+
+        // a region listed twice gets one id, which mustn't hide the special tokens after it
+        let region = |chr: &str, start: u32, end: u32| Region {
+            chr: chr.to_string(),
+            start,
+            end,
+        };
+        let peak13 = region("chr13", 74550022, 74550411);
+        let peak15 = region("chr15", 49155935, 49156182);
+        let mut duplicated = Universe::from(vec![peak13.clone(), peak13.clone(), peak15.clone()]);
+        duplicated.insert_token(&unk);
+        let bed_only = tempfile::tempdir().unwrap();
+        std::fs::copy(tmp_dir.path().join("a.bed"), bed_only.path().join("a.bed")).unwrap();
+        let (pruned, dropped) = duplicated.prune_by_count(bed_only.path(), 0).unwrap();
+        assert_eq!(pruned.regions, vec![peak13, peak15]);
+        assert_eq!(pruned.len(), 3);
+        assert_eq!(pruned.convert_region_to_id(&unk), Some(2));
+        assert!(dropped.is_empty());
+
+        // token files written with this universe are counted, others are rejected
+        let indexed = tmp_dir.path().join("d.gtok");
+        let digest = universe.digest();
+        write_gtok_documents(indexed.to_str().unwrap(), &[vec![chr13]], Some(&digest)).unwrap();
+        let (pruned, _) = universe.prune_by_count(tmp_dir.path(), 3).unwrap();
+        assert_eq!(pruned.len(), 1);
+        write_gtok_documents(indexed.to_str().unwrap(), &[vec![chr13]], Some("other")).unwrap();
+        assert!(universe.prune_by_count(tmp_dir.path(), 3).is_err());
+    }
+
+    #[rstest]
+    fn test_read_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let gtf = dir.path().join("genes.gtf");
+        std::fs::write(
+            &gtf,
+            "#!genome-build GRCh38\n\
+             chr1\tHAVANA\tgene\t11869\t14409\t.\t+\t.\tgene_id \"ENSG00000223972\"; gene_name \"DDX11L1\";\n\
+             chr1\tHAVANA\texon\t11869\t12227\t.\t+\t.\tgene_id \"ENSG00000223972\"; exon_number \"1\";\n\
+             chr1\tHAVANA\tCDS\t12010\t12057\t.\t+\t0\tgene_id \"ENSG00000223972\";\n",
+        )
+        .unwrap();
+
+        let genes = extract_annotations_from_file(&gtf, None, &["gene"]).unwrap();
+        assert_eq!(genes.len(), 1);
+        assert_eq!((genes[0].region.start, genes[0].region.end), (11868, 14409));
+        assert_eq!(genes[0].strand, Strand::Forward);
+        assert_eq!(genes[0].attribute("gene_name"), Some("DDX11L1"));
+
+        let all = extract_annotations_from_file(&gtf, None, &[]).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2].frame, Some(0));
+        assert_eq!(RegionSet::from(all.as_slice()).len(), 3);
+
+        let gff = dir.path().join("genes.gff3");
+        std::fs::write(
+            &gff,
+            "##gff-version 3\n\
+             chr2\tRefSeq\tgene\t100\t200\t0.5\t-\t.\tID=gene1;Name=A%3BB\n\
+             ##FASTA\n>chr2\nACGT\n",
+        )
+        .unwrap();
+
+        let genes = extract_annotations_from_file(&gff, None, &[]).unwrap();
+        assert_eq!(genes.len(), 1);
+        assert_eq!(genes[0].score, Some(0.5));
+        assert_eq!(genes[0].strand, Strand::Reverse);
+        assert_eq!(genes[0].attribute("Name"), Some("A;B"));
+
+        assert!(
+            AnnotationRecord::parse("chr1\tx\tgene\t0\t10\t.\t+\t.\t", AnnotationFormat::Gtf)
+                .is_err()
+        );
+        assert!(extract_annotations_from_file(&dir.path().join("genes.txt"), None, &[]).is_err());
+    }
+
+    #[rstest]
+    fn test_read_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        let vcf = dir.path().join("variants.vcf");
+        std::fs::write(
+            &vcf,
+            "##fileformat=VCFv4.2\n\
+             #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+             chr1\t100\trs1\tA\tG\t50\tPASS\tAF=0.5;DP=20;DB\n\
+             chr1\t5\t.\tACGT\tA,AC\t.\t.\t.\n",
+        )
+        .unwrap();
+
+        let variants = extract_variants_from_file(&vcf, 0, &[]).unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(
+            (variants[0].region.start, variants[0].region.end),
+            (99, 100)
+        );
+        assert_eq!(variants[0].id.as_deref(), Some("rs1"));
+        assert_eq!(variants[0].info.get("AF").map(|v| v.as_str()), Some("0.5"));
+        assert_eq!(variants[0].info.get("DB").map(|v| v.as_str()), Some(""));
+        assert_eq!((variants[1].region.start, variants[1].region.end), (4, 8));
+        assert_eq!(variants[1].alternates, vec!["A", "AC"]);
+        assert!(variants[1].info.is_empty());
+
+        let variants = extract_variants_from_file(&vcf, 10, &["DP"]).unwrap();
+        assert_eq!(
+            (variants[0].region.start, variants[0].region.end),
+            (89, 110)
+        );
+        assert_eq!((variants[1].region.start, variants[1].region.end), (0, 18));
+        assert_eq!(variants[0].info.len(), 1);
+
+        assert_eq!(RegionSet::from(variants.as_slice()).len(), 2);
+
+        // the allele has to end inside the coordinate space
+        let line =
+            |pos: u32, reference: &str| format!("chr1\t{}\t.\t{}\tA\t.\t.\t.", pos, reference);
+        assert!(VariantRecord::parse(&line(u32::MAX, "A"), 0, &[]).is_ok());
+        assert!(VariantRecord::parse(&line(u32::MAX, "AC"), 0, &[]).is_err());
+        assert!(VariantRecord::parse(&line(u32::MAX - 5, "ACGTACG"), 0, &[]).is_err());
+    }
+
+    #[rstest]
+    fn test_bed12_records() {
+        let line =
+            "chr1\t1000\t5000\ttx1\t960\t-\t1200\t4500\t0,0,255\t3\t500,1000,500,\t0,1500,3500,";
+        let record: BedRecord = line.parse().unwrap();
+        assert_eq!(record.name.as_deref(), Some("tx1"));
+        assert_eq!(record.score, Some(960.0));
+        assert_eq!(record.strand, Strand::Reverse);
+
+        let spans = |regions: Vec<Region>| -> Vec<(u32, u32)> {
+            regions.iter().map(|r| (r.start, r.end)).collect()
+        };
+        assert_eq!(
+            spans(record.blocks().unwrap()),
+            vec![(1000, 1500), (2500, 3500), (4500, 5000)]
+        );
+        assert_eq!(
+            spans(record.gaps().unwrap()),
+            vec![(1500, 2500), (3500, 4500)]
+        );
+        assert_eq!(
+            spans(record.thick_blocks().unwrap()),
+            vec![(1200, 1500), (2500, 3500)]
+        );
+
+        // bed6 records are a single block
+        let record: BedRecord = "chr1\t10\t20\tpeak\t0\t+".parse().unwrap();
+        assert_eq!(record.strand, Strand::Forward);
+        assert_eq!(spans(record.blocks().unwrap()), vec![(10, 20)]);
+        assert!(record.gaps().unwrap().is_empty());
+        assert!(record.thick_region().is_none());
+
+        let bed3: BedRecord = "chr1\t10\t20".parse().unwrap();
+        assert_eq!(
+            bed3.region(),
+            Region {
+                chr: "chr1".to_string(),
+                start: 10,
+                end: 20
+            }
+        );
+
+        assert!("chr1\t10\t20\tx\t0\t*".parse::<BedRecord>().is_err());
+        // start and end can't be left empty
+        assert!("chr1\t\t100".parse::<BedRecord>().is_err());
+        assert!("chr1\t10\t".parse::<BedRecord>().is_err());
+        assert!("chr1\t10\t20\tx\t0\t+\t10\t20\t0\t2\t5,5\t0,"
+            .parse::<BedRecord>()
+            .is_err());
+        assert!("chr1\t10\t20\tx\t0\t+\t10\t20\t0\t1\t15\t0"
+            .parse::<BedRecord>()
+            .is_err());
+        // block sums that overflow u32 are errors, not panics
+        assert!("chr1\t10\t20\tx\t0\t+\t10\t20\t0\t1\t5\t4294967295"
+            .parse::<BedRecord>()
+            .is_err());
+        let mut record: BedRecord = "chr1\t10\t20".parse().unwrap();
+        record.blocks = Some(vec![(u32::MAX, 1)]);
+        assert!(record.blocks().is_err());
+    }
+
+    #[rstest]
+    fn test_universe_from_chrom_sizes() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("genome.chrom.sizes");
+        std::fs::write(&path, "chr1\t25\nchr2\t10\n").unwrap();
+
+        let universe = Universe::from_chrom_sizes(&path, 10, None).unwrap();
+        let ends: Vec<(String, u32, u32)> = universe
+            .regions
+            .iter()
+            .map(|r| (r.chr.to_owned(), r.start, r.end))
+            .collect();
+        assert_eq!(
+            ends,
+            vec![
+                ("chr1".to_string(), 0, 10),
+                ("chr1".to_string(), 10, 20),
+                ("chr1".to_string(), 20, 25),
+                ("chr2".to_string(), 0, 10),
+            ]
+        );
+
+        let overlapping = Universe::from_chrom_sizes(&path, 10, Some(5)).unwrap();
+        assert_eq!(overlapping.len(), 7);
+        assert!(Universe::from_chrom_sizes(&path, 0, None).is_err());
+    }
+
+    #[rstest]
+    fn test_build_consensus_universe() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp_dir.path().join("a.bed"),
+            "chr1\t100\t200\nchr2\t0\t50\n",
+        )
+        .unwrap();
+        std::fs::write(tmp_dir.path().join("b.bed"), "chr1\t150\t250\n").unwrap();
+        std::fs::write(
+            tmp_dir.path().join("c.tsv"),
+            "# fragments\nchr1\t180\t300\tAAAC\t1\nchr1\t190\t210\tAAAG\t1\n",
+        )
+        .unwrap();
+        std::fs::write(tmp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let spans = |universe: &Universe| -> Vec<(String, u32, u32)> {
+            universe
+                .regions
+                .iter()
+                .map(|r| (r.chr.to_owned(), r.start, r.end))
+                .collect()
+        };
+
+        let (universe, stats) =
+            build_consensus_universe(tmp_dir.path(), ConsensusMethod::Files(2)).unwrap();
+        assert_eq!(spans(&universe), vec![("chr1".to_string(), 100, 300)]);
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.input_regions, 5);
+        assert_eq!(stats.universe_regions, 1);
+        assert_eq!(stats.universe_bp, 200);
+
+        let (universe, _) =
+            build_consensus_universe(tmp_dir.path(), ConsensusMethod::Coverage(2)).unwrap();
+        assert_eq!(spans(&universe), vec![("chr1".to_string(), 150, 250)]);
+
+        let (universe, _) =
+            build_consensus_universe(tmp_dir.path(), ConsensusMethod::Coverage(3)).unwrap();
+        assert_eq!(spans(&universe), vec![("chr1".to_string(), 180, 200)]);
+
+        assert!(build_consensus_universe(tmp_dir.path(), ConsensusMethod::Files(0)).is_err());
+
+        std::fs::write(tmp_dir.path().join("d.bed"), "chr1\t500\t400\n").unwrap();
+        assert!(build_consensus_universe(tmp_dir.path(), ConsensusMethod::Files(2)).is_err());
+    }
+
+    #[rstest]
+    fn test_tokenize_with_overlap_options(path_to_bed_file: &str) {
+        let mut tokenizer = TreeTokenizer::try_from(Path::new(path_to_bed_file)).unwrap();
+        let universe = tokenizer.get_universe();
+        let small = universe
+            .convert_chr_start_end_to_id("chr15", 49155856, 49155887)
+            .unwrap();
+        let large = universe
+            .convert_chr_start_end_to_id("chr15", 49155935, 49156182)
+            .unwrap();
+        let unk = tokenizer.unknown_token_id();
+
+        // overlaps the small peak by 31bp and the large one by 247bp
+        let region = Region {
+            chr: "chr15".to_string(),
+            start: 49155846,
+            end: 49156192,
+        };
+        let mut tokenize = |options: OverlapOptions| {
+            tokenizer.set_overlap_options(options);
+            tokenizer.tokenize_region(&region).ids
+        };
+
+        assert_eq!(tokenize(OverlapOptions::default()), vec![small, large]);
+        let min_bp = OverlapOptions {
+            min_bp: 100,
+            ..Default::default()
+        };
+        assert_eq!(tokenize(min_bp), vec![large]);
+        let min_fraction = OverlapOptions {
+            min_fraction: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(tokenize(min_fraction), vec![large]);
+        let best = OverlapOptions {
+            policy: OverlapPolicy::Best,
+            ..Default::default()
+        };
+        assert_eq!(tokenize(best), vec![large]);
+        let too_strict = OverlapOptions {
+            min_fraction: 0.9,
+            ..Default::default()
+        };
+        assert_eq!(tokenize(too_strict), vec![unk]);
+    }
+
     #[rstest]
     fn test_init_gtok_file(path_to_gtok_file: &str) {
         let res = init_gtok_file(path_to_gtok_file);